@@ -0,0 +1,90 @@
+use codecrafters_interpreter::interpret;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn empty_block_loop(c: &mut Criterion) {
+    let source = r#"
+        var total = 0;
+        for (var i = 0; i < 10000; i = i + 1) {
+            { total = total + i; }
+        }
+    "#;
+    c.bench_function("empty_block_loop", |b| {
+        b.iter(|| interpret(source).unwrap())
+    });
+}
+
+fn deep_method_lookup(c: &mut Criterion) {
+    let source = r#"
+        class A { value() { return 1; } }
+        class B < A {}
+        class C < B {}
+        class D < C {}
+        class E < D {}
+        var e = E();
+        var total = 0;
+        for (var i = 0; i < 10000; i = i + 1) {
+            total = total + e.value();
+        }
+    "#;
+    c.bench_function("deep_method_lookup", |b| {
+        b.iter(|| interpret(source).unwrap())
+    });
+}
+
+fn fibonacci(c: &mut Criterion) {
+    let source = r#"
+        fun fib(n) {
+            if (n < 2) return n;
+            return fib(n - 1) + fib(n - 2);
+        }
+        fib(25);
+    "#;
+    let mut group = c.benchmark_group("heavy");
+    group.sample_size(10);
+    group.bench_function("fibonacci_25", |b| b.iter(|| interpret(source).unwrap()));
+    group.finish();
+}
+
+fn counting_loop(c: &mut Criterion) {
+    let source = r#"
+        var count = 0;
+        while (count < 1000000) count = count + 1;
+    "#;
+    let mut group = c.benchmark_group("heavy");
+    group.sample_size(10);
+    group.bench_function("counting_loop_1m", |b| {
+        b.iter(|| interpret(source).unwrap())
+    });
+    group.finish();
+}
+
+fn method_calls(c: &mut Criterion) {
+    let source = r#"
+        class Counter {
+            init() { this.count = 0; }
+            increment() { this.count = this.count + 1; return this; }
+            get() { return this.count; }
+        }
+        class Pair {
+            init(a, b) { this.a = a; this.b = b; }
+            sum() { return this.a.get() + this.b.get(); }
+        }
+        var pair = Pair(Counter(), Counter());
+        for (var i = 0; i < 10000; i = i + 1) {
+            pair.a.increment();
+            pair.b.increment().increment();
+            pair.sum();
+        }
+    "#;
+    c.bench_function("method_calls", |b| b.iter(|| interpret(source).unwrap()));
+}
+
+criterion_group!(
+    benches,
+    empty_block_loop,
+    deep_method_lookup,
+    fibonacci,
+    counting_loop,
+    method_calls
+);
+criterion_main!(benches);
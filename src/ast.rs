@@ -1,28 +1,59 @@
 use crate::TokenKind;
 
+/// Every statement but `Empty` records the line it starts on.
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Expr(Expression),
-    Block(Vec<Statement>),
+    Empty,
+    Expr {
+        expression: Expression,
+        line: usize,
+    },
+    Block {
+        statements: Vec<Statement>,
+        /// Whether the block needs its own environment. The parser assumes it
+        /// does; the resolver clears this for blocks that declare nothing.
+        scoped: bool,
+        line: usize,
+    },
     Class {
         name: String,
         superclass: Option<String>,
         methods: Vec<Statement>,
+        statics: Vec<(String, Expression)>,
+        line: usize,
+    },
+    Print {
+        expressions: Vec<Expression>,
+        line: usize,
+    },
+    /// `import "path";`, with the path relative to the importing file.
+    Import {
+        path: String,
+        line: usize,
     },
-    Print(Expression),
     Var {
         name: String,
         initializer: Option<Expression>,
+        line: usize,
     },
 
     If {
         condition: Expression,
         then_branch: Box<Statement>,
         else_branch: Option<Box<Statement>>,
+        line: usize,
     },
     While {
         condition: Box<Expression>,
         body: Box<Statement>,
+        label: Option<String>,
+        line: usize,
+    },
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expression,
+        label: Option<String>,
+        line: usize,
     },
 
     For {
@@ -30,18 +61,67 @@ pub enum Statement {
         condition: Option<Expression>,
         increment: Option<Expression>,
         body: Box<Statement>,
+        label: Option<String>,
+        line: usize,
+    },
+    ForIn {
+        name: String,
+        iterable: Expression,
+        body: Box<Statement>,
+        label: Option<String>,
+        line: usize,
+    },
+    Break {
+        label: Option<String>,
+        line: usize,
+    },
+    Continue {
+        label: Option<String>,
+        line: usize,
+    },
+    Switch {
+        discriminant: Expression,
+        cases: Vec<(Expression, Vec<Statement>)>,
+        default: Option<Vec<Statement>>,
+        line: usize,
     },
     Function {
         name: String,
         params: Vec<String>,
         body: Vec<Statement>,
+        line: usize,
     },
 
     Return {
         value: Option<Expression>,
+        line: usize,
     },
 }
 
+impl Statement {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Statement::Empty => None,
+            Statement::Expr { line, .. }
+            | Statement::Block { line, .. }
+            | Statement::Class { line, .. }
+            | Statement::Print { line, .. }
+            | Statement::Import { line, .. }
+            | Statement::Var { line, .. }
+            | Statement::If { line, .. }
+            | Statement::While { line, .. }
+            | Statement::DoWhile { line, .. }
+            | Statement::For { line, .. }
+            | Statement::ForIn { line, .. }
+            | Statement::Break { line, .. }
+            | Statement::Continue { line, .. }
+            | Statement::Switch { line, .. }
+            | Statement::Function { line, .. }
+            | Statement::Return { line, .. } => Some(*line),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Expression {
     Assign {
@@ -55,6 +135,8 @@ pub enum Expression {
         right: Box<Expression>,
     },
     Literal(Literal),
+    List(Vec<Expression>),
+    Map(Vec<(String, Expression)>),
     Unary {
         operator: TokenKind,
         expression: Box<Expression>,
@@ -64,6 +146,11 @@ pub enum Expression {
         name: String,
         resolved: Option<usize>,
     },
+    Postfix {
+        name: String,
+        operator: TokenKind,
+        resolved: Option<usize>,
+    },
     Logical {
         left: Box<Expression>,
         operator: TokenKind,
@@ -73,6 +160,7 @@ pub enum Expression {
     Call {
         callee: Box<Expression>,
         args: Vec<Expression>,
+        line: usize,
     },
 
     Set {
@@ -84,6 +172,18 @@ pub enum Expression {
         object: Box<Expression>,
         name: String,
     },
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+    },
+    Lambda {
+        params: Vec<String>,
+        body: Vec<Statement>,
+    },
+    Block {
+        statements: Vec<Statement>,
+        value: Box<Expression>,
+    },
     This {
         resolved: Option<usize>,
     },
@@ -101,35 +201,85 @@ pub enum Literal {
     Nil,
 }
 
+/// A one-line, source-like summary. Bodies are left out; they show up as
+/// statements of their own when they run.
 impl std::fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = |label: &Option<String>| match label {
+            Some(label) => format!("{label}: "),
+            None => String::new(),
+        };
         match self {
-            Statement::Expr(expr) => write!(f, "{expr}"),
-            Statement::Print(expr) => write!(f, "{expr}"),
-            Statement::Var { name, initializer } => write!(f, "{name}: {:?}", initializer),
-            Statement::Block(list) => write!(f, "{list:?}"),
-            Statement::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => write!(
-                f,
-                "condition:{}, then: {}, else {:?}",
-                condition, then_branch, else_branch
-            ),
-            Statement::While { condition, body } => {
-                write!(f, "condition {}, body {}", condition, body)
+            Statement::Empty => write!(f, ";"),
+            Statement::Expr { expression, .. } => write!(f, "{};", Source(expression)),
+            Statement::Print { expressions, .. } => {
+                let parts: Vec<String> = expressions
+                    .iter()
+                    .map(|expr| Source(expr).to_string())
+                    .collect();
+                write!(f, "print {};", parts.join(", "))
             }
-
+            Statement::Import { path, .. } => write!(f, "import {path:?};"),
+            Statement::Var {
+                name,
+                initializer: Some(initializer),
+                ..
+            } => write!(f, "var {name} = {};", Source(initializer)),
+            Statement::Var { name, .. } => write!(f, "var {name};"),
+            Statement::Block { statements, .. } => {
+                write!(f, "{{ {} statements }}", statements.len())
+            }
+            Statement::If { condition, .. } => write!(f, "if ({})", Source(condition)),
+            Statement::While {
+                condition, label, ..
+            } => write!(f, "{}while ({})", prefix(label), Source(condition)),
+            Statement::DoWhile {
+                condition, label, ..
+            } => write!(f, "{}do while ({})", prefix(label), Source(condition)),
             Statement::For {
-                initialize,
                 condition,
                 increment,
-                body,
-            } => write!(f, "init :{initialize:?} condition:{condition:?} increment: {increment:?} body {body:?}"),
-            Statement::Function { name, params, body } => {write!(f, "function {name}({params:?}){body:?}")},
-            Statement::Return { value }=> write!(f, "{value:?}"),
-            Statement::Class { name, methods: _, superclass:_ } => write!(f, "{name}")
+                label,
+                ..
+            } => {
+                let condition = condition
+                    .as_ref()
+                    .map(|c| Source(c).to_string())
+                    .unwrap_or_default();
+                let increment = increment
+                    .as_ref()
+                    .map(|i| Source(i).to_string())
+                    .unwrap_or_default();
+                write!(f, "{}for (; {condition}; {increment})", prefix(label))
+            }
+            Statement::ForIn {
+                name,
+                iterable,
+                label,
+                ..
+            } => write!(f, "{}for ({name} in {})", prefix(label), Source(iterable)),
+            Statement::Switch { discriminant, .. } => write!(f, "when ({})", Source(discriminant)),
+            Statement::Function { name, params, .. } => {
+                write!(f, "fun {name}({})", params.join(", "))
+            }
+            Statement::Return {
+                value: Some(value), ..
+            } => write!(f, "return {};", Source(value)),
+            Statement::Return { value: None, .. } => write!(f, "return;"),
+            Statement::Break {
+                label: Some(label), ..
+            } => write!(f, "break {label};"),
+            Statement::Break { label: None, .. } => write!(f, "break;"),
+            Statement::Continue {
+                label: Some(label), ..
+            } => write!(f, "continue {label};"),
+            Statement::Continue { label: None, .. } => write!(f, "continue;"),
+            Statement::Class {
+                name,
+                superclass: Some(superclass),
+                ..
+            } => write!(f, "class {name} < {superclass}"),
+            Statement::Class { name, .. } => write!(f, "class {name}"),
         }
     }
 }
@@ -138,7 +288,9 @@ impl std::fmt::Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Literal::Number(n) => {
-                if n.fract() == 0.0 {
+                if n.is_nan() {
+                    write!(f, "nan")
+                } else if n.fract() == 0.0 {
                     write!(f, "{:.1}", n)
                 } else {
                     write!(f, "{n}")
@@ -151,43 +303,56 @@ impl std::fmt::Display for Literal {
     }
 }
 
+/// The source spelling of an operator, or the token's name for kinds that
+/// have no fixed spelling.
+fn operator_symbol(kind: &TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Plus => "+",
+        TokenKind::PlusEqual => "+=",
+        TokenKind::PlusPlus => "++",
+        TokenKind::Minus => "-",
+        TokenKind::MinusMinus => "--",
+        TokenKind::Star => "*",
+        TokenKind::Slash => "/",
+        TokenKind::Bang => "!",
+        TokenKind::BangEqual => "!=",
+        TokenKind::Equal => "=",
+        TokenKind::EqualEqual => "==",
+        TokenKind::Greater => ">",
+        TokenKind::GreaterEqual => ">=",
+        TokenKind::Less => "<",
+        TokenKind::LessEqual => "<=",
+        TokenKind::And => "and",
+        TokenKind::Or => "or",
+        TokenKind::QuestionQuestion => "??",
+        other => other.name(),
+    }
+}
+
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expression::Literal(l) => write!(f, "{l}"),
+            Expression::List(elements) => write!(f, "{elements:?}"),
+            Expression::Map(entries) => write!(f, "{entries:?}"),
             Expression::Group(expr) => write!(f, "(group {expr})"),
             Expression::Binary {
                 left,
                 operator,
                 right,
             } => {
-                let op_str = match operator {
-                    TokenKind::Plus => "+",
-                    TokenKind::Minus => "-",
-                    TokenKind::Star => "*",
-                    TokenKind::Slash => "/",
-                    TokenKind::BangEqual => "!=",
-                    TokenKind::EqualEqual => "==",
-                    TokenKind::Greater => ">",
-                    TokenKind::GreaterEqual => ">=",
-                    TokenKind::Less => "<",
-                    TokenKind::LessEqual => "<=",
-                    _ => unimplemented!(),
-                };
-                write!(f, "({} {} {})", op_str, left, right)
+                write!(f, "({} {} {})", operator_symbol(operator), left, right)
             }
             Expression::Unary {
                 operator,
                 expression,
             } => {
-                let op = match operator {
-                    TokenKind::Bang => "!",
-                    TokenKind::Minus => "-",
-                    _ => unimplemented!(),
-                };
-                write!(f, "({} {})", op, expression)
+                write!(f, "({} {})", operator_symbol(operator), expression)
             }
             Expression::Variable { name, resolved: _ } => write!(f, "{name:?}"),
+            Expression::Postfix { name, operator, .. } => {
+                write!(f, "{name:?}{}", operator_symbol(operator))
+            }
             Expression::Assign {
                 name,
                 value,
@@ -198,15 +363,171 @@ impl std::fmt::Display for Expression {
                 operator,
                 right,
             } => write!(f, "{} {:?} {}", lelf, operator, right),
-            Expression::Call { callee, args } => write!(f, "{}, {:?}", callee, args),
+            Expression::Call { callee, args, .. } => write!(f, "{}, {:?}", callee, args),
             Expression::Set {
                 object,
                 property,
                 value,
             } => write!(f, "{object:?} {property} {value:?}"),
             Expression::Get { object, name } => write!(f, "{object:?}.{name}"),
+            Expression::Index { object, index } => write!(f, "{object}[{index}]"),
+            Expression::Lambda { params, body } => write!(f, "fun({params:?}){body:?}"),
+            Expression::Block { statements, value } => write!(f, "{{{statements:?} {value}}}"),
             Expression::This { resolved } => write!(f, "{resolved:?}"),
             Expression::Super { resolved, method } => write!(f, "{:?} {}", resolved, method),
         }
     }
 }
+
+/// Writes an expression back out roughly as it was written, for messages
+/// meant for people rather than for the `parse` command.
+struct Source<'a>(&'a Expression);
+
+impl std::fmt::Display for Source<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |exprs: &[Expression]| {
+            exprs
+                .iter()
+                .map(|expr| Source(expr).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self.0 {
+            Expression::Literal(Literal::String(s)) => write!(f, "\"{s}\""),
+            Expression::Literal(Literal::Number(n)) => write!(f, "{n}"),
+            Expression::Literal(literal) => write!(f, "{literal}"),
+            Expression::List(elements) => write!(f, "[{}]", list(elements)),
+            Expression::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("\"{key}\": {}", Source(value)))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Expression::Group(expr) => write!(f, "({})", Source(expr)),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expression::Logical {
+                left,
+                operator,
+                right,
+            } => write!(
+                f,
+                "{} {} {}",
+                Source(left),
+                operator_symbol(operator),
+                Source(right)
+            ),
+            Expression::Unary {
+                operator,
+                expression,
+            } => write!(f, "{}{}", operator_symbol(operator), Source(expression)),
+            Expression::Variable { name, .. } => write!(f, "{name}"),
+            Expression::Postfix { name, operator, .. } => {
+                write!(f, "{name}{}", operator_symbol(operator))
+            }
+            Expression::Assign { name, value, .. } => write!(f, "{name} = {}", Source(value)),
+            Expression::Call { callee, args, .. } => {
+                write!(f, "{}({})", Source(callee), list(args))
+            }
+            Expression::Set {
+                object,
+                property,
+                value,
+            } => write!(f, "{}.{property} = {}", Source(object), Source(value)),
+            Expression::Get { object, name } => write!(f, "{}.{name}", Source(object)),
+            Expression::Index { object, index } => {
+                write!(f, "{}[{}]", Source(object), Source(index))
+            }
+            Expression::Lambda { params, .. } => write!(f, "fun ({}) {{ ... }}", params.join(", ")),
+            Expression::Block { value, .. } => write!(f, "{{ ... {} }}", Source(value)),
+            Expression::This { .. } => write!(f, "this"),
+            Expression::Super { method, .. } => write!(f, "super.{method}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, Literal, TokenKind};
+
+    #[test]
+    fn non_finite_literals_display() {
+        assert_eq!(Literal::Number(f64::NAN).to_string(), "nan");
+        assert_eq!(Literal::Number(f64::INFINITY).to_string(), "inf");
+        assert_eq!(Literal::Number(f64::NEG_INFINITY).to_string(), "-inf");
+    }
+
+    fn number(n: f64) -> Box<Expression> {
+        Box::new(Expression::Literal(Literal::Number(n)))
+    }
+
+    #[test]
+    fn binary_operators_display() {
+        let cases = [
+            (TokenKind::Plus, "+"),
+            (TokenKind::Minus, "-"),
+            (TokenKind::Star, "*"),
+            (TokenKind::Slash, "/"),
+            (TokenKind::BangEqual, "!="),
+            (TokenKind::EqualEqual, "=="),
+            (TokenKind::Greater, ">"),
+            (TokenKind::GreaterEqual, ">="),
+            (TokenKind::Less, "<"),
+            (TokenKind::LessEqual, "<="),
+            (TokenKind::Comma, "COMMA"),
+        ];
+
+        for (operator, symbol) in cases {
+            let expr = Expression::Binary {
+                left: number(1.0),
+                operator,
+                right: number(2.0),
+            };
+            assert_eq!(expr.to_string(), format!("({symbol} 1.0 2.0)"));
+        }
+    }
+
+    #[test]
+    fn unary_and_postfix_operators_display() {
+        for (operator, symbol) in [
+            (TokenKind::Bang, "!"),
+            (TokenKind::Minus, "-"),
+            (TokenKind::Plus, "+"),
+        ] {
+            let expr = Expression::Unary {
+                operator,
+                expression: number(5.0),
+            };
+            assert_eq!(expr.to_string(), format!("({symbol} 5.0)"));
+        }
+
+        for (operator, symbol) in [(TokenKind::PlusPlus, "++"), (TokenKind::MinusMinus, "--")] {
+            let expr = Expression::Postfix {
+                name: "i".to_string(),
+                operator,
+                resolved: None,
+            };
+            assert_eq!(expr.to_string(), format!("\"i\"{symbol}"));
+        }
+    }
+
+    #[test]
+    fn operators_without_a_spelling_fall_back_to_token_names() {
+        let expr = Expression::Unary {
+            operator: TokenKind::Identifier,
+            expression: number(1.0),
+        };
+        assert_eq!(expr.to_string(), "(IDENTIFIER 1.0)");
+
+        let expr = Expression::Binary {
+            left: number(1.0),
+            operator: TokenKind::Semi,
+            right: number(2.0),
+        };
+        assert_eq!(expr.to_string(), "(SEMICOLON 1.0 2.0)");
+    }
+}
@@ -1,26 +1,47 @@
-use crate::{Callable, InterpreterError, Value};
+use std::{ops::RangeInclusive, rc::Rc};
 
-#[derive(Debug, Clone)]
+use crate::{Callable, Interpreter, InterpreterError, Value};
+
+pub type NativeFn = Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, InterpreterError>>;
+
+#[derive(Clone)]
 pub struct NativeFunction {
-    pub arity: usize,
+    pub arity: RangeInclusive<usize>,
     pub name: String,
-    pub function: fn(Vec<Value>) -> Result<Value, InterpreterError>,
+    pub function: NativeFn,
+}
+
+impl std::fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("arity", &self.arity)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Callable for NativeFunction {
     fn call(
         &self,
-        _interpreter: &mut crate::Interpreter,
+        interpreter: &mut Interpreter,
         args: Vec<Value>,
     ) -> Result<Value, InterpreterError> {
-        (self.function)(args)
+        (self.function)(interpreter, args)
     }
 
     fn arity(&self) -> usize {
-        self.arity
+        *self.arity.start()
+    }
+
+    fn arity_range(&self) -> RangeInclusive<usize> {
+        self.arity.clone()
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
+
+    fn is_native(&self) -> bool {
+        true
+    }
 }
@@ -1,3 +1,5 @@
+use std::ops::RangeInclusive;
+
 use crate::{Interpreter, InterpreterError, Value};
 
 pub trait Callable: std::fmt::Debug {
@@ -9,5 +11,14 @@ pub trait Callable: std::fmt::Debug {
 
     fn arity(&self) -> usize;
 
+    /// The accepted argument counts; an end of `usize::MAX` means variadic.
+    fn arity_range(&self) -> RangeInclusive<usize> {
+        self.arity()..=self.arity()
+    }
+
     fn name(&self) -> String;
+
+    fn is_native(&self) -> bool {
+        false
+    }
 }
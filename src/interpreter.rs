@@ -1,13 +1,17 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    io::Write,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
 };
 
+use indexmap::IndexMap;
+
 use crate::{
-    Callable, Expression, Literal, LoxClass, LoxInstance, NativeFunction, Resolver, Statement,
-    TokenKind, Visitor,
+    constant_fold, natives, show_nul, Callable, Expression, Literal, LoxClass, LoxInstance,
+    NativeFunction, Parser, Resolver, Statement, TokenKind, Visitor,
 };
 
 #[derive(Debug)]
@@ -19,6 +23,8 @@ pub enum Value {
     Function(Rc<dyn Callable>),
     Class(Rc<LoxClass>),
     Instance(Rc<LoxInstance>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<IndexMap<String, Value>>>),
 }
 
 impl Clone for Value {
@@ -31,11 +37,13 @@ impl Clone for Value {
             Self::Function(f) => Self::Function(f.clone()),
             Self::Class(class) => Self::Class(class.clone()),
             Self::Instance(instance) => Self::Instance(instance.clone()),
+            Self::List(list) => Self::List(list.clone()),
+            Self::Map(map) => Self::Map(map.clone()),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExitCode {
     RunTimeError,
     CompilerError,
@@ -54,7 +62,43 @@ impl From<ExitCode> for i32 {
 pub enum InterpreterError {
     Message(String, ExitCode),
     UndefinedVariable(String),
+    NotCallable { what: String, line: usize },
     ReturnError(Value),
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+enum LoopFlow {
+    Next,
+    Exit,
+}
+
+/// Decides whether a loop labelled `label` consumes a `break`/`continue`
+/// raised by its body, passing any other error through.
+fn loop_flow(
+    result: Result<(), InterpreterError>,
+    label: Option<&str>,
+) -> Result<LoopFlow, InterpreterError> {
+    let targets = |target: &Option<String>| target.is_none() || target.as_deref() == label;
+    match result {
+        Ok(()) => Ok(LoopFlow::Next),
+        Err(InterpreterError::Break(target)) if targets(&target) => Ok(LoopFlow::Exit),
+        Err(InterpreterError::Continue(target)) if targets(&target) => Ok(LoopFlow::Next),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum EnvError {
+    Undefined(String),
+}
+
+impl From<EnvError> for InterpreterError {
+    fn from(value: EnvError) -> Self {
+        match value {
+            EnvError::Undefined(name) => InterpreterError::UndefinedVariable(name),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -72,22 +116,29 @@ pub struct LoxFunction {
     is_initializer: bool,
 }
 
+impl LoxFunction {
+    /// This function as a method of `instance`.
+    pub fn bind(&self, instance: Rc<LoxInstance>) -> BoundMethod {
+        BoundMethod {
+            function: Rc::new(self.clone()),
+            instance,
+        }
+    }
+}
+
 impl Callable for LoxFunction {
     fn call(
         &self,
         interpreter: &mut Interpreter,
         args: Vec<Value>,
     ) -> Result<Value, InterpreterError> {
-        let old_env = interpreter.environment.clone();
-        let new_env = Environment::new_enclosed(&self.environment.clone());
+        let new_env = Environment::new_enclosed(&self.environment);
 
         for (name, value) in self.params.iter().zip(args.iter()) {
             new_env.borrow_mut().define(name.as_str(), value.clone());
         }
 
-        interpreter.environment = new_env;
-        let result = interpreter.visit_block(&self.body);
-        interpreter.environment = old_env;
+        let result = EnvGuard::new(interpreter, new_env).visit_block(&self.body);
 
         match result {
             Ok(_) => Ok(Value::Nil),
@@ -117,19 +168,17 @@ impl Callable for BoundMethod {
         interpreter: &mut Interpreter,
         args: Vec<Value>,
     ) -> Result<Value, InterpreterError> {
-        let old_env = interpreter.environment.clone();
         let new_env = Environment::new_enclosed(&self.function.environment);
 
+        // `this` lives in the same scope as the parameters, as in the resolver.
+        new_env
+            .borrow_mut()
+            .define("this", Value::Instance(self.instance.clone()));
         for (name, value) in self.function.params.iter().zip(args.iter()) {
             new_env.borrow_mut().define(name.as_str(), value.clone());
         }
 
-        new_env
-            .borrow_mut()
-            .define("this", Value::Instance(self.instance.clone()));
-        interpreter.environment = new_env;
-        let result = interpreter.visit_block(&self.function.body);
-        interpreter.environment = old_env;
+        let result = EnvGuard::new(interpreter, new_env).visit_block(&self.function.body);
 
         match result {
             Ok(_) => {
@@ -182,54 +231,237 @@ impl Environment {
         self.values.insert(name.to_string(), value);
     }
 
-    pub fn get(&self, name: &str) -> Option<Value> {
+    pub fn get(&self, name: &str) -> Result<Value, EnvError> {
         if let Some(value) = self.values.get(name) {
-            Some(value.clone())
+            Ok(value.clone())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow().get(name)
         } else {
-            None
+            Err(EnvError::Undefined(name.to_string()))
         }
     }
 
-    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), EnvError> {
         if self.values.contains_key(name) {
             self.values.insert(name.to_string(), value);
-            true
+            Ok(())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
         } else {
-            false
+            Err(EnvError::Undefined(name.to_string()))
         }
     }
 }
 
 pub struct Interpreter {
     environment: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
     pub locals: HashMap<String, usize>,
+    loop_limit: Option<usize>,
+    loop_iterations: usize,
+    trace: bool,
+    trace_output: Box<dyn Write>,
+    float_epsilon: Option<f64>,
+    // Everything registered through `define_native` and friends, which is
+    // all `reset` keeps.
+    natives: Vec<(String, Value)>,
+    // The file being run, which `import` paths are relative to.
+    source_path: Option<PathBuf>,
+    // Files already run, so repeated and cyclic imports are skipped.
+    imported: HashSet<PathBuf>,
+}
+
+/// Runs the interpreter in `environment` until dropped, then restores the
+/// previous environment, whether the scope exited normally or with an error.
+struct EnvGuard<'a> {
+    interpreter: &'a mut Interpreter,
+    previous: Rc<RefCell<Environment>>,
+}
+
+impl<'a> EnvGuard<'a> {
+    fn new(interpreter: &'a mut Interpreter, environment: Rc<RefCell<Environment>>) -> Self {
+        let previous = std::mem::replace(&mut interpreter.environment, environment);
+        EnvGuard {
+            interpreter,
+            previous,
+        }
+    }
+
+    /// Enters a fresh scope enclosed by the current one.
+    fn enclosed(interpreter: &'a mut Interpreter) -> Self {
+        let environment = Environment::new_enclosed(&interpreter.environment);
+        Self::new(interpreter, environment)
+    }
+}
+
+impl std::ops::Deref for EnvGuard<'_> {
+    type Target = Interpreter;
+
+    fn deref(&self) -> &Interpreter {
+        self.interpreter
+    }
+}
+
+impl std::ops::DerefMut for EnvGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Interpreter {
+        self.interpreter
+    }
+}
+
+impl Drop for EnvGuard<'_> {
+    fn drop(&mut self) {
+        self.interpreter.environment = self.previous.clone();
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
         let global = Rc::new(RefCell::new(Environment::new()));
-        global.borrow_mut().define(
-            "clock",
-            Value::Function(Rc::new(NativeFunction {
-                name: "clock".to_string(),
-                arity: 0,
-                function: |_| {
-                    let start_time = SystemTime::now();
-                    let since_the_epoch = start_time
-                        .duration_since(UNIX_EPOCH)
-                        .expect("Time went backwards");
-                    Ok(Value::Number(since_the_epoch.as_secs_f64()))
-                },
-            })),
-        );
-        Interpreter {
-            environment: global,
+        let mut interpreter = Interpreter {
+            environment: global.clone(),
+            globals: global,
             locals: HashMap::new(),
+            loop_limit: None,
+            loop_iterations: 0,
+            trace: false,
+            trace_output: Box::new(std::io::stderr()),
+            float_epsilon: None,
+            natives: Vec::new(),
+            source_path: None,
+            imported: HashSet::new(),
+        };
+        natives::register(&mut interpreter);
+        interpreter
+    }
+
+    /// Drops everything a program defined, keeping natives (built-in or added
+    /// with `define_native`) and settings such as the loop limit.
+    pub fn reset(&mut self) {
+        let mut globals = Environment::new();
+        for (name, value) in &self.natives {
+            globals.define(name, value.clone());
         }
+        self.globals = Rc::new(RefCell::new(globals));
+        self.environment = self.globals.clone();
+        self.locals.clear();
+        self.loop_iterations = 0;
+        self.imported.clear();
+        self.imported.extend(self.source_path.clone());
+    }
+
+    /// Records the file the program comes from, so `import` can find files
+    /// next to it and won't import it again.
+    pub fn set_source_path(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.imported.insert(path.clone());
+        self.source_path = Some(path);
+    }
+
+    /// A snapshot of the global environment, regardless of the current scope.
+    pub fn globals(&self) -> HashMap<String, Value> {
+        self.globals.borrow().values.clone()
+    }
+
+    /// Caps the total number of loop body executions; `None` means unlimited.
+    pub fn set_loop_limit(&mut self, limit: Option<usize>) {
+        self.loop_limit = limit;
+    }
+
+    /// Logs each statement and its line to the trace output (stderr by
+    /// default) before executing it.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    pub fn set_trace_output(&mut self, output: impl Write + 'static) {
+        self.trace_output = Box::new(output);
+    }
+
+    /// When set, `==`, `!=` and `when` cases treat numbers within `epsilon` of
+    /// each other as equal. Off by default.
+    pub fn set_float_epsilon(&mut self, epsilon: Option<f64>) {
+        self.float_epsilon = epsilon;
+    }
+
+    fn is_equal(&self, left: &Value, right: &Value) -> bool {
+        match (left, right, self.float_epsilon) {
+            // Compare exactly first: `inf - inf` is NaN.
+            (Value::Number(l), Value::Number(r), Some(epsilon)) => {
+                l == r || (l - r).abs() <= epsilon
+            }
+            _ => left.equals(right),
+        }
+    }
+
+    fn count_iteration(&mut self) -> Result<(), InterpreterError> {
+        self.loop_iterations += 1;
+        match self.loop_limit {
+            Some(limit) if self.loop_iterations > limit => Err(InterpreterError::Message(
+                "Loop iteration limit exceeded.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(Vec<Value>) -> Result<Value, InterpreterError> + 'static,
+    ) {
+        self.define_variadic_native(name, arity..=arity, function);
+    }
+
+    pub fn define_variadic_native(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(Vec<Value>) -> Result<Value, InterpreterError> + 'static,
+    ) {
+        self.define_interpreter_native(name, arity, move |_, args| function(args));
+    }
+
+    /// Like `define_variadic_native`, for natives that need the interpreter.
+    pub fn define_interpreter_native(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, InterpreterError> + 'static,
+    ) {
+        let native = Value::Function(Rc::new(NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        }));
+        self.natives.retain(|(existing, _)| existing != name);
+        self.natives.push((name.to_string(), native.clone()));
+        self.globals.borrow_mut().define(name, native);
+    }
+
+    /// Reads a binding from the global scope, ignoring any shadowing locals.
+    pub fn global(&self, name: &str) -> Result<Value, InterpreterError> {
+        Ok(self.globals.borrow().get(name)?)
+    }
+
+    /// Calls the global function `name`, as a Lox call expression would.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        let function = match self.global(name)? {
+            Value::Function(function) => function,
+            other => {
+                return Err(InterpreterError::Message(
+                    format!("'{name}' is a {}, not a function.", other.type_name()),
+                    ExitCode::RunTimeError,
+                ))
+            }
+        };
+        Self::check_arity(function.as_ref(), args.len())?;
+        function.call(self, args)
     }
 }
 
@@ -252,11 +484,13 @@ impl Visitor<Value, InterpreterError> for Interpreter {
     ) -> Result<Value, InterpreterError> {
         let left_value = self.evaluate(left)?;
 
-        if *operator == TokenKind::Or {
-            if is_truthy(&left_value) {
-                return Ok(left_value);
-            }
-        } else if *operator == TokenKind::And && !is_truthy(&left_value) {
+        let short_circuits = match operator {
+            TokenKind::Or => left_value.is_truthy(),
+            TokenKind::And => !left_value.is_truthy(),
+            TokenKind::QuestionQuestion => !matches!(left_value, Value::Nil),
+            _ => false,
+        };
+        if short_circuits {
             return Ok(left_value);
         }
 
@@ -267,25 +501,42 @@ impl Visitor<Value, InterpreterError> for Interpreter {
         &mut self,
         condition: &Expression,
         body: &Statement,
+        label: Option<&str>,
     ) -> Result<(), InterpreterError> {
-        while is_truthy(&self.evaluate(condition)?) {
-            self.visit_stmt(body)?;
+        while self.evaluate(condition)?.is_truthy() {
+            self.count_iteration()?;
+            if let LoopFlow::Exit = loop_flow(self.visit_stmt(body), label)? {
+                break;
+            }
         }
 
         Ok(())
     }
 
     fn visit_stmt(&mut self, stms: &Statement) -> Result<(), InterpreterError> {
+        if let (true, Some(line)) = (self.trace, stms.line()) {
+            writeln!(self.trace_output, "[trace] [line {line}] {stms}")
+                .map_err(|e| InterpreterError::Message(e.to_string(), ExitCode::RunTimeError))?;
+        }
+
         match stms {
-            Statement::Print(expr) => {
-                let eval = self.evaluate(expr)?;
-                println!("{eval}");
+            Statement::Empty => {}
+            Statement::Import { path, .. } => self.import(path)?,
+            Statement::Print { expressions, .. } => {
+                let mut parts = Vec::with_capacity(expressions.len());
+                for expr in expressions {
+                    let eval = self.evaluate(expr)?;
+                    parts.push(self.stringify(&eval)?);
+                }
+                println!("{}", parts.join(" "));
             }
-            Statement::Expr(expr) => {
-                let _result = self.visit_expr(expr)?;
+            Statement::Expr { expression, .. } => {
+                let _result = self.visit_expr(expression)?;
             }
 
-            Statement::Var { name, initializer } => {
+            Statement::Var {
+                name, initializer, ..
+            } => {
                 let value = if let Some(expr) = initializer {
                     self.visit_expr(expr)?
                 } else {
@@ -295,66 +546,131 @@ impl Visitor<Value, InterpreterError> for Interpreter {
                 self.environment.borrow_mut().define(name.as_str(), value);
             }
 
-            Statement::Block(list) => {
-                let new_env = Environment::new_enclosed(&self.environment);
-                let old_env = self.environment.clone();
-                self.environment = new_env;
-                self.visit_block(list)?;
-
-                self.environment = old_env;
+            Statement::Block {
+                statements,
+                scoped: false,
+                ..
+            } => self.visit_block(statements)?,
+            Statement::Block { statements, .. } => {
+                EnvGuard::enclosed(self).visit_block(statements)?
             }
 
             Statement::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 self.visit_if_stms(condition, then_branch, else_branch)?;
             }
 
-            Statement::While { condition, body } => self.visit_while(condition, body)?,
+            Statement::While {
+                condition,
+                body,
+                label,
+                ..
+            } => self.visit_while(condition, body, label.as_deref())?,
+            Statement::DoWhile {
+                body,
+                condition,
+                label,
+                ..
+            } => loop {
+                self.count_iteration()?;
+                if let LoopFlow::Exit = loop_flow(self.visit_stmt(body), label.as_deref())? {
+                    break;
+                }
+                if !self.evaluate(condition)?.is_truthy() {
+                    break;
+                }
+            },
+            Statement::Break { label, .. } => return Err(InterpreterError::Break(label.clone())),
+            Statement::Continue { label, .. } => {
+                return Err(InterpreterError::Continue(label.clone()))
+            }
             Statement::For {
                 initialize,
                 condition,
                 increment,
                 body,
+                label,
+                ..
             } => {
                 let previous = self.environment.clone();
-                let loop_env = Environment::new_enclosed(&previous);
-                self.environment = loop_env;
-
-                if let Some(init) = initialize {
-                    self.visit_stmt(init)?;
-                }
+                EnvGuard::new(self, Environment::new_enclosed(&previous)).run_for(
+                    initialize,
+                    condition,
+                    increment,
+                    body,
+                    label.as_deref(),
+                    &previous,
+                )?;
+            }
 
-                loop {
-                    if let Some(con) = condition {
-                        if !is_truthy(&self.evaluate(con)?) {
-                            break;
-                        }
+            Statement::ForIn {
+                name,
+                iterable,
+                body,
+                label,
+                ..
+            } => {
+                let items = match self.evaluate(iterable)? {
+                    Value::List(list) => list.borrow().clone(),
+                    Value::Map(map) => map.borrow().keys().cloned().map(Value::String).collect(),
+                    _ => {
+                        return Err(InterpreterError::Message(
+                            "Can only iterate over lists and maps.".to_string(),
+                            ExitCode::RunTimeError,
+                        ))
                     }
+                };
 
-                    self.visit_stmt(body)?;
+                let previous = self.environment.clone();
+                for item in items {
+                    self.count_iteration()?;
+                    let loop_env = Environment::new_enclosed(&previous);
+                    loop_env.borrow_mut().define(name, item);
+                    let result = EnvGuard::new(self, loop_env).visit_stmt(body);
+                    if let LoopFlow::Exit = loop_flow(result, label.as_deref())? {
+                        break;
+                    }
+                }
+            }
 
-                    if let Some(inc) = increment {
-                        self.evaluate(inc)?;
+            Statement::Switch {
+                discriminant,
+                cases,
+                default,
+                ..
+            } => {
+                let value = self.evaluate(discriminant)?;
+                let mut matched = default.as_deref();
+                for (case, body) in cases {
+                    let case = self.evaluate(case)?;
+                    if self.is_equal(&value, &case) {
+                        matched = Some(body);
+                        break;
                     }
                 }
 
-                self.environment = previous;
+                if let Some(body) = matched {
+                    EnvGuard::enclosed(self).visit_block(body)?;
+                }
             }
 
-            Statement::Function { name, params, body } => {
-                self.visit_function_stms(name, params, body)
-            }
+            Statement::Function {
+                name, params, body, ..
+            } => self.visit_function_stms(name, params, body),
 
-            Statement::Return { value } => self.visit_return_stms(value)?,
+            Statement::Return { value, .. } => self.visit_return_stms(value)?,
 
             Statement::Class {
                 name,
                 superclass,
                 methods,
-            } => self.visit_class(name.as_str(), superclass.as_deref(), methods)?,
+                statics,
+                ..
+            } => self.visit_class(name.as_str(), superclass.as_deref(), methods, statics)?,
         }
 
         Ok(())
@@ -372,50 +688,59 @@ impl Visitor<Value, InterpreterError> for Interpreter {
         then_branch: &Statement,
         else_branch: &Option<Box<Statement>>,
     ) -> Result<(), InterpreterError> {
-        if is_truthy(&self.evaluate(condition)?) {
-            self.visit_stmt(then_branch)?;
-        } else if let Some(stms) = else_branch {
-            self.visit_stmt(stms)?;
+        let (mut condition, mut then_branch, mut else_branch) =
+            (condition, then_branch, else_branch);
+        // `else if` chains are followed in a loop so they don't grow the stack.
+        loop {
+            if self.evaluate(condition)?.is_truthy() {
+                return self.visit_stmt(then_branch);
+            }
+            match else_branch.as_deref() {
+                Some(Statement::If {
+                    condition: next_condition,
+                    then_branch: next_then,
+                    else_branch: next_else,
+                    ..
+                }) => {
+                    condition = next_condition;
+                    then_branch = next_then;
+                    else_branch = next_else;
+                }
+                Some(statement) => return self.visit_stmt(statement),
+                None => return Ok(()),
+            }
         }
-
-        Ok(())
     }
 
     fn visit_call_expr(
         &mut self,
         callee: &Expression,
         args: &[Expression],
+        line: usize,
     ) -> Result<Value, InterpreterError> {
-        let callee_value = self.evaluate(callee)?;
-        if let Value::Function(function) = callee_value {
-            if function.arity() != args.len() {
-                return Err(InterpreterError::Message(
-                    format!(
-                        "Expected {} arguments but got {}.",
-                        function.arity(),
-                        args.len(),
-                    ),
-                    ExitCode::RunTimeError,
-                ));
+        let function: Rc<dyn Callable> = match self.evaluate(callee)? {
+            Value::Function(function) => function,
+            Value::Class(class) => {
+                let mut arg_values = Vec::new();
+                for arg_expr in args {
+                    arg_values.push(self.evaluate(arg_expr)?);
+                }
+                return class.call(self, arg_values);
             }
+            Value::Instance(instance) => match instance.find_method("call") {
+                Some(method) => Rc::new(method.bind(instance)),
+                None => return Err(Self::not_callable("instance", line)),
+            },
+            other => return Err(Self::not_callable(other.type_name(), line)),
+        };
 
-            let mut arg_values = Vec::new();
-            for arg_expr in args {
-                arg_values.push(self.evaluate(arg_expr)?);
-            }
-            function.call(self, arg_values)
-        } else if let Value::Class(class) = callee_value {
-            let mut arg_values = Vec::new();
-            for arg_expr in args {
-                arg_values.push(self.evaluate(arg_expr)?);
-            }
-            class.call(self, arg_values)
-        } else {
-            Err(InterpreterError::Message(
-                "Can only call functions and classes.".to_string(),
-                ExitCode::RunTimeError,
-            ))
+        Self::check_arity(function.as_ref(), args.len())?;
+
+        let mut arg_values = Vec::new();
+        for arg_expr in args {
+            arg_values.push(self.evaluate(arg_expr)?);
         }
+        function.call(self, arg_values)
     }
 
     fn visit_function_stms(&mut self, name: &str, params: &[String], body: &[Statement]) {
@@ -447,6 +772,7 @@ impl Visitor<Value, InterpreterError> for Interpreter {
         name: &str,
         superclass: Option<&str>,
         methods: &[Statement],
+        statics: &[(String, Expression)],
     ) -> Result<(), InterpreterError> {
         self.environment.borrow_mut().define(name, Value::Nil);
 
@@ -455,6 +781,7 @@ impl Visitor<Value, InterpreterError> for Interpreter {
                 self.environment
                     .borrow()
                     .get(super_name)
+                    .ok()
                     .and_then(|v| match v {
                         Value::Class(c) => Some(c.clone()),
                         _ => None,
@@ -476,32 +803,41 @@ impl Visitor<Value, InterpreterError> for Interpreter {
                 .borrow_mut()
                 .define("super", Value::Class(sclass.clone()));
         }
-        let closure_rc = Rc::new(RefCell::new(closure));
 
         let class = LoxClass::new(name.to_string(), superclass_value);
+        for (static_name, value) in statics {
+            let value = self.evaluate(value)?;
+            class.define_static(static_name.clone(), value);
+        }
         for method in methods {
             match method {
                 Statement::Function {
                     name: method_name,
                     params,
                     body,
+                    ..
                 } => {
                     let function = LoxFunction {
                         name: method_name.clone(),
                         params: params.clone(),
                         body: body.clone(),
-                        environment: closure_rc.borrow().clone(),
+                        environment: closure.clone(),
                         is_initializer: method_name == "init",
                     };
 
                     class.create_method(method_name.clone(), function);
                 }
-                _ => unreachable!(),
+                _ => {
+                    return Err(InterpreterError::Message(
+                        format!("Class '{name}' has a member that is not a method."),
+                        ExitCode::RunTimeError,
+                    ))
+                }
             }
         }
 
         let value = Value::Class(Rc::new(class));
-        self.environment.borrow_mut().assign(name, value);
+        self.environment.borrow_mut().assign(name, value)?;
 
         Ok(())
     }
@@ -514,6 +850,13 @@ impl Visitor<Value, InterpreterError> for Interpreter {
         let value = self.evaluate(expr)?;
         match value {
             Value::Instance(instance) => instance.get(&name),
+            Value::Class(class) => class.get_static(&name).ok_or_else(|| {
+                InterpreterError::Message(
+                    format!("Undefined property '{}'.", name),
+                    ExitCode::RunTimeError,
+                )
+            }),
+            Value::String(s) => Self::string_property(s, &name),
             _ => Err(InterpreterError::Message(
                 "Only instances have properties.".to_string(),
                 ExitCode::RunTimeError,
@@ -543,25 +886,222 @@ impl Visitor<Value, InterpreterError> for Interpreter {
 }
 
 impl Interpreter {
-    pub fn run(mut stmt: Vec<Statement>) -> Result<(), InterpreterError> {
-        let interpreter = Interpreter::new();
-        let mut resolver = Resolver::new(interpreter);
-        if let Err(e) = resolver.resolve_stmts(&mut stmt[..]) {
+    fn check_arity(function: &dyn Callable, count: usize) -> Result<(), InterpreterError> {
+        let arity = function.arity_range();
+        if arity.contains(&count) {
+            return Ok(());
+        }
+        let expected = if arity.start() == arity.end() {
+            arity.start().to_string()
+        } else if *arity.end() == usize::MAX {
+            format!("at least {}", arity.start())
+        } else {
+            format!("{} to {}", arity.start(), arity.end())
+        };
+        Err(InterpreterError::Message(
+            format!("Expected {} arguments but got {}.", expected, count),
+            ExitCode::RunTimeError,
+        ))
+    }
+
+    /// Runs the top level of the file at `path` in the global scope.
+    fn import(&mut self, path: &str) -> Result<(), InterpreterError> {
+        let base = self.source_path.as_deref().and_then(Path::parent);
+        let full_path = base.unwrap_or(Path::new("")).join(path);
+        let cannot_import = |err: std::io::Error| {
+            InterpreterError::Message(
+                format!("Can't import '{path}': {err}."),
+                ExitCode::RunTimeError,
+            )
+        };
+
+        let full_path = full_path.canonicalize().map_err(cannot_import)?;
+        if !self.imported.insert(full_path.clone()) {
+            return Ok(());
+        }
+        let source = std::fs::read_to_string(&full_path).map_err(cannot_import)?;
+        let statements = Parser::new(&source).parse_statements().map_err(|err| {
+            InterpreterError::Message(format!("In '{path}': {err}"), ExitCode::CompilerError)
+        })?;
+
+        let importer = self.source_path.replace(full_path);
+        let globals = self.globals.clone();
+        let result = EnvGuard::new(self, globals).execute(statements);
+        self.source_path = importer;
+        result
+    }
+
+    fn not_callable(what: &str, line: usize) -> InterpreterError {
+        InterpreterError::NotCallable {
+            what: what.to_string(),
+            line,
+        }
+    }
+
+    fn run_for(
+        &mut self,
+        initialize: &Option<Box<Statement>>,
+        condition: &Option<Expression>,
+        increment: &Option<Expression>,
+        body: &Statement,
+        label: Option<&str>,
+        enclosing: &Rc<RefCell<Environment>>,
+    ) -> Result<(), InterpreterError> {
+        if let Some(init) = initialize {
+            self.visit_stmt(init)?;
+        }
+
+        loop {
+            if let Some(con) = condition {
+                if !self.evaluate(con)?.is_truthy() {
+                    break;
+                }
+            }
+
+            self.count_iteration()?;
+            if let LoopFlow::Exit = loop_flow(self.visit_stmt(body), label)? {
+                break;
+            }
+
+            // Copy the loop variables into a fresh environment so closures
+            // created by this iteration keep their own bindings.
+            let next = Environment::new_enclosed(enclosing);
+            next.borrow_mut().values = self.environment.borrow().values.clone();
+            self.environment = next;
+
+            if let Some(inc) = increment {
+                self.evaluate(inc)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        object: &Expression,
+        index: &Expression,
+    ) -> Result<Value, InterpreterError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        match (object, index) {
+            (Value::List(list), Value::Number(n)) => {
+                let list = list.borrow();
+                let position = Self::index_position("List", n, list.len())?;
+                Ok(list[position].clone())
+            }
+            (Value::String(s), Value::Number(n)) => {
+                let position = Self::index_position("String", n, s.chars().count())?;
+                Ok(Value::String(s.chars().nth(position).unwrap().to_string()))
+            }
+            (Value::Map(map), Value::String(key)) => {
+                Ok(map.borrow().get(&key).cloned().unwrap_or(Value::Nil))
+            }
+            (Value::List(_), _) => Err(InterpreterError::Message(
+                "List index must be a number.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+            (Value::String(_), _) => Err(InterpreterError::Message(
+                "String index must be a number.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+            (Value::Map(_), _) => Err(InterpreterError::Message(
+                "Map key must be a string.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+            _ => Err(InterpreterError::Message(
+                "Only lists, strings and maps can be indexed.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+        }
+    }
+
+    /// Strings' pseudo-properties: `length`, and `upper`/`lower` methods bound
+    /// to the string.
+    fn string_property(s: String, name: &str) -> Result<Value, InterpreterError> {
+        let function: fn(&str) -> String = match name {
+            "length" => return Ok(Value::Number(s.chars().count() as f64)),
+            "upper" => str::to_uppercase,
+            "lower" => str::to_lowercase,
+            _ => {
+                return Err(InterpreterError::Message(
+                    format!("Undefined property '{}'.", name),
+                    ExitCode::RunTimeError,
+                ))
+            }
+        };
+        Ok(Value::Function(Rc::new(NativeFunction {
+            arity: 0..=0,
+            name: name.to_string(),
+            function: Rc::new(move |_, _| Ok(Value::String(function(&s)))),
+        })))
+    }
+
+    /// Turns an index into a position in a sequence of `len` items, counting
+    /// negative indices back from the end.
+    fn index_position(what: &str, n: f64, len: usize) -> Result<usize, InterpreterError> {
+        if n.fract() != 0.0 {
+            return Err(InterpreterError::Message(
+                format!("{what} index must be an integer."),
+                ExitCode::RunTimeError,
+            ));
+        }
+        let position = if n < 0.0 { n + len as f64 } else { n };
+        if position < 0.0 || position >= len as f64 {
+            return Err(InterpreterError::Message(
+                "Index out of range.".to_string(),
+                ExitCode::RunTimeError,
+            ));
+        }
+        Ok(position as usize)
+    }
+
+    pub fn run(stmt: Vec<Statement>) -> Result<(), InterpreterError> {
+        Interpreter::new().execute(stmt)
+    }
+
+    /// Resolves and runs a program on this interpreter, keeping its settings
+    /// and globals.
+    pub fn execute(&mut self, mut stmt: Vec<Statement>) -> Result<(), InterpreterError> {
+        if let Err(e) = Resolver::new().resolve_stmts(&mut stmt[..]) {
             return Err(InterpreterError::Message(
                 format!("Resolution error: {}", e),
                 ExitCode::CompilerError,
             ));
         }
 
-        let mut interpreter = resolver.interpreter;
+        constant_fold(&mut stmt);
 
         for st in stmt.iter() {
-            interpreter.visit_stmt(st)?;
+            self.visit_stmt(st)?;
         }
 
         Ok(())
     }
 
+    /// Parses, resolves and evaluates a single expression against this
+    /// interpreter's globals.
+    pub fn execute_expr(&mut self, source: &str) -> Result<Value, InterpreterError> {
+        let mut expr = Parser::new(source)
+            .parse()
+            .map_err(|e| InterpreterError::Message(e.to_string(), ExitCode::CompilerError))?;
+
+        self.evaluate_resolved(&mut expr)
+    }
+
+    /// Like `evaluate`, but resolves the expression first, as `execute` does
+    /// for statements.
+    pub fn evaluate_resolved(&mut self, expr: &mut Expression) -> Result<Value, InterpreterError> {
+        if let Err(e) = Resolver::new().resolve_single_expr(expr) {
+            return Err(InterpreterError::Message(
+                format!("Resolution error: {}", e),
+                ExitCode::CompilerError,
+            ));
+        }
+
+        self.evaluate(expr)
+    }
+
     pub fn resolve(&mut self, name: &str, distance: usize) {
         self.locals.insert(name.to_string(), distance);
     }
@@ -617,55 +1157,120 @@ impl Interpreter {
             .insert(name.to_string(), value);
     }
 
+    /// Like `Display`, but lets instances override their text with a
+    /// `toString` method.
+    pub fn stringify(&mut self, value: &Value) -> Result<String, InterpreterError> {
+        if let Value::Instance(instance) = value {
+            if let Some(method) = instance.find_method("toString") {
+                let bound = method.bind(instance.clone());
+                return Ok(bound.call(self, vec![])?.to_string());
+            }
+        }
+
+        Ok(value.to_string())
+    }
+
+    fn look_up_variable(
+        &self,
+        name: &str,
+        resolved: Option<usize>,
+    ) -> Result<Value, InterpreterError> {
+        if let Some(distance) = resolved {
+            self.get_at(self.environment.clone(), distance, name)
+                .ok_or_else(|| InterpreterError::UndefinedVariable(name.to_string()))
+        } else {
+            Ok(self.environment.borrow().get(name)?)
+        }
+    }
+
+    fn assign_variable(
+        &mut self,
+        name: &str,
+        resolved: Option<usize>,
+        value: Value,
+    ) -> Result<(), InterpreterError> {
+        if let Some(distance) = resolved {
+            self.assign_at(self.environment.clone(), distance, name, value);
+            Ok(())
+        } else {
+            Ok(self.environment.borrow_mut().assign(name, value)?)
+        }
+    }
+
     pub fn evaluate(&mut self, expr: &Expression) -> Result<Value, InterpreterError> {
         match expr {
             Expression::Literal(literal) => self.visit_literal_expr(literal),
+            Expression::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::List(Rc::new(RefCell::new(values))))
+            }
+            Expression::Map(entries) => {
+                let mut map = IndexMap::with_capacity(entries.len());
+                for (key, value) in entries {
+                    map.insert(key.clone(), self.evaluate(value)?);
+                }
+                Ok(Value::Map(Rc::new(RefCell::new(map))))
+            }
             Expression::Unary {
                 operator,
                 expression,
             } => self.visit_unary_expr(expression, operator),
             Expression::Group(inner_expr) => self.evaluate(inner_expr),
-            Expression::Variable { name, resolved } => {
-                if let Some(distance) = *resolved {
-                    self.get_at(self.environment.clone(), distance, name.as_str())
-                        .ok_or_else(|| InterpreterError::UndefinedVariable(name.clone()))
-                } else {
-                    self.environment
-                        .borrow()
-                        .get(name.as_str())
-                        .ok_or_else(|| InterpreterError::UndefinedVariable(name.clone()))
-                }
-            }
+            Expression::Variable { name, resolved } => self.look_up_variable(name, *resolved),
             Expression::Assign {
                 name,
                 value,
                 resolved,
             } => {
                 let new_value = self.evaluate(value)?;
-                if let Some(distance) = *resolved {
-                    self.assign_at(
-                        self.environment.clone(),
-                        distance,
-                        name.as_str(),
-                        new_value.clone(),
-                    );
-                    Ok(new_value)
-                } else {
-                    let mut env = self.environment.borrow_mut();
-                    if env.assign(name.as_str(), new_value.clone()) {
-                        Ok(new_value)
-                    } else {
-                        Err(InterpreterError::UndefinedVariable(name.clone()))
+                self.assign_variable(name, *resolved, new_value.clone())?;
+                Ok(new_value)
+            }
+            Expression::Postfix {
+                name,
+                operator,
+                resolved,
+            } => {
+                let old_value = match self.look_up_variable(name, *resolved)? {
+                    Value::Number(n) => n,
+                    _ => {
+                        return Err(InterpreterError::Message(
+                            "Operand must be a number.".to_string(),
+                            ExitCode::RunTimeError,
+                        ))
                     }
-                }
+                };
+                let new_value = if *operator == TokenKind::PlusPlus {
+                    old_value + 1.0
+                } else {
+                    old_value - 1.0
+                };
+                self.assign_variable(name, *resolved, Value::Number(new_value))?;
+                Ok(Value::Number(old_value))
             }
             Expression::Logical {
                 left,
                 operator,
                 right,
             } => self.visit_logical(left, operator, right),
-            Expression::Call { callee, args } => self.visit_call_expr(callee, args),
+            Expression::Call { callee, args, line } => self.visit_call_expr(callee, args, *line),
             Expression::Get { object, name } => self.visit_get_expr(object, name.clone()),
+            Expression::Index { object, index } => self.visit_index_expr(object, index),
+            Expression::Lambda { params, body } => Ok(Value::Function(Rc::new(LoxFunction {
+                name: "lambda".to_string(),
+                params: params.clone(),
+                body: body.clone(),
+                environment: self.environment.clone(),
+                is_initializer: false,
+            }))),
+            Expression::Block { statements, value } => {
+                let mut scope = EnvGuard::enclosed(self);
+                scope.visit_block(statements)?;
+                scope.evaluate(value)
+            }
             Expression::Set {
                 object,
                 property,
@@ -715,14 +1320,12 @@ impl Interpreter {
                     }
                 };
 
-                let this_val =
-                    self.environment
-                        .borrow()
-                        .get("this")
-                        .ok_or(InterpreterError::Message(
-                            "Cannot use 'super' in a static context.".to_string(),
-                            ExitCode::RunTimeError,
-                        ))?;
+                let this_val = self.environment.borrow().get("this").map_err(|_| {
+                    InterpreterError::Message(
+                        "Cannot use 'super' in a static context.".to_string(),
+                        ExitCode::RunTimeError,
+                    )
+                })?;
 
                 let this_instance = match this_val {
                     Value::Instance(i) => i,
@@ -734,19 +1337,24 @@ impl Interpreter {
                     }
                 };
 
+                // Fields live on the instance, so only methods are found through `super`.
                 let method_func = super_class.find_method(method).ok_or_else(|| {
                     InterpreterError::Message(
-                        format!("Undefined property '{}'.", method),
+                        format!(
+                            "Superclass '{}' has no method '{}'.",
+                            super_class.name, method
+                        ),
                         ExitCode::RunTimeError,
                     )
                 })?;
 
-                Ok(Value::Function(Rc::new(BoundMethod {
-                    function: Rc::new(method_func),
-                    instance: this_instance,
-                })))
+                Ok(Value::Function(Rc::new(method_func.bind(this_instance))))
             }
-            Expression::Binary { .. } => self.visit_binary_expr(expr),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => self.visit_binary_expr(left, operator, right),
         }
     }
 
@@ -784,50 +1392,79 @@ impl Interpreter {
         }
     }
 
-    fn visit_binary_expr(&mut self, expr: &Expression) -> Result<Value, InterpreterError> {
-        if let Expression::Binary {
-            left,
-            operator,
-            right,
-        } = expr
-        {
-            let left = self.evaluate(left)?;
-            let right = self.evaluate(right)?;
-            match (left, operator, right) {
-                (Value::Number(n), TokenKind::Plus, Value::Number(n1)) => Ok(Value::Number(n + n1)),
-                (Value::String(s), TokenKind::Plus, Value::String(s1)) => {
-                    let s = format!("{s}{s1}");
-                    Ok(Value::String(s))
-                }
-                (Value::Number(n), TokenKind::Minus, Value::Number(n1)) => {
-                    Ok(Value::Number(n - n1))
-                }
-                (Value::Number(n), TokenKind::Star, Value::Number(n1)) => Ok(Value::Number(n * n1)),
-                (Value::Number(n), TokenKind::Slash, Value::Number(n1)) => {
-                    Ok(Value::Number(n / n1))
-                }
-                (Value::Number(n), TokenKind::Greater, Value::Number(n1)) => {
-                    Ok(Value::Boolean(n > n1))
-                }
-                (Value::Number(n), TokenKind::Less, Value::Number(n1)) => {
-                    Ok(Value::Boolean(n < n1))
-                }
-                (Value::Number(n), TokenKind::GreaterEqual, Value::Number(n1)) => {
-                    Ok(Value::Boolean(n >= n1))
-                }
-                (Value::Number(n), TokenKind::LessEqual, Value::Number(n1)) => {
-                    Ok(Value::Boolean(n <= n1))
-                }
-
-                (l, TokenKind::EqualEqual, r) => Ok(Value::Boolean(is_equal(&l, &r))),
-                (l, TokenKind::BangEqual, r) => Ok(Value::Boolean(!is_equal(&l, &r))),
-                _ => Err(InterpreterError::Message(
-                    "Unsupported operation".to_string(),
-                    ExitCode::RunTimeError,
-                )),
+    fn visit_binary_expr(
+        &mut self,
+        left: &Expression,
+        operator: &TokenKind,
+        right: &Expression,
+    ) -> Result<Value, InterpreterError> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        let operator = match (&left, operator, &right) {
+            (Value::List(list), TokenKind::PlusEqual, Value::List(other)) => {
+                let extra = other.borrow().clone();
+                list.borrow_mut().extend(extra);
+                return Ok(left);
             }
-        } else {
-            unreachable!()
+            (_, TokenKind::PlusEqual, _) => &TokenKind::Plus,
+            _ => operator,
+        };
+        match (left, operator, right) {
+            (Value::Number(n), TokenKind::Plus, Value::Number(n1)) => Ok(Value::Number(n + n1)),
+            (Value::List(l), TokenKind::Plus, Value::List(r)) => {
+                let mut values = l.borrow().clone();
+                values.extend(r.borrow().iter().cloned());
+                Ok(Value::list(values))
+            }
+            (Value::String(s), TokenKind::Plus, Value::String(s1)) => {
+                let s = format!("{s}{s1}");
+                Ok(Value::String(s))
+            }
+            (Value::Number(n), TokenKind::Minus, Value::Number(n1)) => Ok(Value::Number(n - n1)),
+            (Value::Number(n), TokenKind::Star, Value::Number(n1)) => Ok(Value::Number(n * n1)),
+            (Value::Number(n), TokenKind::Slash, Value::Number(n1)) => Ok(Value::Number(n / n1)),
+            (Value::Number(n), TokenKind::Greater, Value::Number(n1)) => Ok(Value::Boolean(n > n1)),
+            (Value::Number(n), TokenKind::Less, Value::Number(n1)) => Ok(Value::Boolean(n < n1)),
+            (Value::Number(n), TokenKind::GreaterEqual, Value::Number(n1)) => {
+                Ok(Value::Boolean(n >= n1))
+            }
+            (Value::Number(n), TokenKind::LessEqual, Value::Number(n1)) => {
+                Ok(Value::Boolean(n <= n1))
+            }
+
+            (l, TokenKind::EqualEqual, r) => Ok(Value::Boolean(self.is_equal(&l, &r))),
+            (l, TokenKind::BangEqual, r) => Ok(Value::Boolean(!self.is_equal(&l, &r))),
+            (_, TokenKind::Plus, _) => Err(InterpreterError::Message(
+                "Operands must be two numbers or two strings.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+            _ => Err(InterpreterError::Message(
+                "Operands must be numbers.".to_string(),
+                ExitCode::RunTimeError,
+            )),
+        }
+    }
+}
+
+impl InterpreterError {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            InterpreterError::Message(_, code) => *code,
+            // The resolver rejects top-level returns, so one escaping to the
+            // caller is a runtime fault rather than a compile error.
+            InterpreterError::UndefinedVariable(_)
+            | InterpreterError::NotCallable { .. }
+            | InterpreterError::ReturnError(_)
+            | InterpreterError::Break(_)
+            | InterpreterError::Continue(_) => ExitCode::RunTimeError,
+        }
+    }
+
+    /// The source line the error happened on, for errors that know it.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            InterpreterError::NotCallable { line, .. } => Some(*line),
+            _ => None,
         }
     }
 }
@@ -835,44 +1472,737 @@ impl Interpreter {
 impl std::fmt::Display for InterpreterError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            InterpreterError::UndefinedVariable(s) => write!(f, "Undefined variable '{s}'"),
+            InterpreterError::UndefinedVariable(s) => write!(f, "Undefined variable '{s}'."),
+            InterpreterError::NotCallable { what, .. } => {
+                write!(f, "Can only call functions and classes, got {what}.")
+            }
             InterpreterError::Message(s, _) => write!(f, "{s}"),
             InterpreterError::ReturnError(v) => write!(f, "{v}"),
+            InterpreterError::Break(_) => write!(f, "Can't use 'break' outside of a loop."),
+            InterpreterError::Continue(_) => {
+                write!(f, "Can't use 'continue' outside of a loop.")
+            }
         }
     }
 }
 
 impl std::error::Error for InterpreterError {}
 
+impl Value {
+    pub fn number(n: f64) -> Self {
+        Value::Number(n)
+    }
+
+    pub fn string(s: impl Into<String>) -> Self {
+        Value::String(s.into())
+    }
+
+    pub fn boolean(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+
+    pub fn list(values: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn as_number(&self) -> Result<f64, InterpreterError> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            _ => Err(Self::operand_error("number")),
+        }
+    }
+
+    pub fn as_string(&self) -> Result<&str, InterpreterError> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err(Self::operand_error("string")),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, InterpreterError> {
+        match self {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(Self::operand_error("boolean")),
+        }
+    }
+
+    fn operand_error(expected: &str) -> InterpreterError {
+        InterpreterError::Message(
+            format!("Operand must be a {expected}."),
+            ExitCode::RunTimeError,
+        )
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::Nil => "nil",
+            Value::String(_) => "string",
+            Value::Function(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+
+    /// Lox truthiness: `nil` and `false` are falsey, everything else is truthy.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::Value;
+    ///
+    /// assert!(!Value::Nil.is_truthy());
+    /// assert!(!Value::Boolean(false).is_truthy());
+    /// assert!(Value::Number(0.0).is_truthy());
+    /// assert!(Value::String(String::new()).is_truthy());
+    /// ```
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Boolean(v) => *v,
+            Value::Nil => false,
+            _ => true,
+        }
+    }
+
+    /// Lox equality: values of different types are never equal.
+    ///
+    /// ```
+    /// use codecrafters_interpreter::Value;
+    ///
+    /// assert!(Value::Nil.equals(&Value::Nil));
+    /// assert!(Value::Number(1.0).equals(&Value::Number(1.0)));
+    /// assert!(!Value::Number(1.0).equals(&Value::String("1".to_string())));
+    /// assert!(!Value::Nil.equals(&Value::Boolean(false)));
+    /// ```
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Nil, _) | (_, Value::Nil) => false,
+            (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
+            // IEEE semantics: NaN is never equal to anything, itself included.
+            (Value::Number(n1), Value::Number(n2)) => n1 == n2,
+            (Value::String(s1), Value::String(s2)) => s1 == s2,
+            (Value::List(l1), Value::List(l2)) => Rc::ptr_eq(l1, l2),
+            (Value::Map(m1), Value::Map(m2)) => Rc::ptr_eq(m1, m2),
+            _ => false,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_number()
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_bool()
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = InterpreterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            _ => Err(Value::operand_error("string")),
+        }
+    }
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            // Rust spells NaN in capitals; the reference prints `nan`.
+            Value::Number(v) if v.is_nan() => write!(f, "nan"),
             Value::Number(v) => write!(f, "{v}"),
             Value::Boolean(v) => write!(f, "{v}"),
             Value::Nil => write!(f, "nil"),
-            Value::String(v) => write!(f, "{v}"),
+            Value::String(v) => write!(f, "{}", show_nul(v)),
+            Value::Function(fun) if fun.is_native() => write!(f, "<native fn>"),
             Value::Function(fun) => write!(f, "<fn {}>", fun.name()),
             Value::Class(class) => write!(f, "{}", class.name()),
             Value::Instance(ins) => write!(f, "{}", ins.name()),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
-fn is_truthy(value: &Value) -> bool {
-    match value {
-        Value::Boolean(v) => *v,
-        Value::Nil => false,
-        _ => true,
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{EnvError, EnvGuard, Environment};
+    use crate::{
+        Callable, ExitCode, Interpreter, InterpreterError, Parser, Statement, Value, Visitor,
+    };
+
+    fn evaluate(interpreter: &mut Interpreter, source: &str) -> Value {
+        let expr = Parser::new(source).parse().unwrap();
+        interpreter.evaluate(&expr).unwrap()
+    }
+
+    #[test]
+    fn execute_expr_evaluates_source() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.execute_expr("(1 + 2) * 3").unwrap();
+        assert!(value.equals(&Value::Number(9.0)));
+
+        let err = interpreter.execute_expr("(1 +").unwrap_err();
+        assert_eq!(err.exit_code(), ExitCode::CompilerError);
+    }
+
+    #[test]
+    fn float_epsilon_equality() {
+        let mut interpreter = Interpreter::new();
+        assert!(evaluate(&mut interpreter, "0.1 + 0.2 == 0.3").equals(&Value::Boolean(false)));
+
+        interpreter.set_float_epsilon(Some(1e-9));
+        assert!(evaluate(&mut interpreter, "0.1 + 0.2 == 0.3").equals(&Value::Boolean(true)));
+        assert!(evaluate(&mut interpreter, "0.1 + 0.2 != 0.3").equals(&Value::Boolean(false)));
+        assert!(evaluate(&mut interpreter, "1 == 1.001").equals(&Value::Boolean(false)));
+        assert!(evaluate(&mut interpreter, "\"1\" == 1").equals(&Value::Boolean(false)));
+        assert!(evaluate(&mut interpreter, "1/0 == 1/0").equals(&Value::Boolean(true)));
+        assert!(evaluate(&mut interpreter, "-1/0 == -1/0").equals(&Value::Boolean(true)));
+        assert!(evaluate(&mut interpreter, "1/0 == -1/0").equals(&Value::Boolean(false)));
+
+        interpreter.set_float_epsilon(None);
+        assert!(evaluate(&mut interpreter, "0.1 + 0.2 == 0.3").equals(&Value::Boolean(false)));
+    }
+
+    #[test]
+    fn reset_keeps_only_natives() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native("answer", 0, |_| Ok(Value::Number(42.0)));
+        let program = Parser::new(
+            "var user = 1; fun f() {} class C {} var alias = clock; var answer_copy = answer; \
+             var sqrt = \"shadowed\";",
+        )
+        .parse_statements()
+        .unwrap();
+        interpreter.execute(program).unwrap();
+        assert!(interpreter.global("user").is_ok());
+
+        interpreter.reset();
+
+        for name in ["user", "f", "C", "alias", "answer_copy"] {
+            assert!(interpreter.global(name).is_err(), "{name}");
+        }
+        assert!(interpreter.global("clock").is_ok());
+        assert_eq!(
+            evaluate(&mut interpreter, "sqrt").to_string(),
+            "<native fn>"
+        );
+        assert!(evaluate(&mut interpreter, "answer()").equals(&Value::Number(42.0)));
+    }
+
+    #[test]
+    fn block_errors_restore_the_outer_scope() {
+        let mut interpreter = Interpreter::new();
+        let program = Parser::new("var a = 1; { var a = 2; { var b = a; nil(); } }")
+            .parse_statements()
+            .unwrap();
+        assert!(interpreter.execute(program).is_err());
+
+        // A leaked block scope would capture this definition instead of globals.
+        let program = Parser::new("var c = 3;").parse_statements().unwrap();
+        interpreter.execute(program).unwrap();
+        assert!(interpreter.global("c").unwrap().equals(&Value::Number(3.0)));
+        assert!(evaluate(&mut interpreter, "a").equals(&Value::Number(1.0)));
+    }
+
+    #[test]
+    fn env_guard_restores_on_every_exit() {
+        let mut interpreter = Interpreter::new();
+        let globals = interpreter.environment.clone();
+        {
+            let scope = EnvGuard::enclosed(&mut interpreter);
+            assert!(!Rc::ptr_eq(&scope.environment, &globals));
+        }
+        assert!(Rc::ptr_eq(&interpreter.environment, &globals));
+
+        let program = Parser::new(
+            "fun ok() { { return 1; } } fun fail() { { var x = 1; nil(); } } \
+             class C { ok() { return this; } fail() { fail(); } }",
+        )
+        .parse_statements()
+        .unwrap();
+        interpreter.execute(program).unwrap();
+
+        for (source, succeeds) in [
+            ("ok()", true),
+            ("fail()", false),
+            ("C().ok()", true),
+            ("C().fail()", false),
+            ("{ var a = 1; a }", true),
+            ("{ var a = 1; a() }", false),
+        ] {
+            assert_eq!(
+                interpreter.execute_expr(source).is_ok(),
+                succeeds,
+                "{source}"
+            );
+            assert!(Rc::ptr_eq(&interpreter.environment, &globals), "{source}");
+        }
+    }
+
+    #[test]
+    fn value_display() {
+        let mut interpreter = Interpreter::new();
+        let program = Parser::new("class Point {} fun add(a, b) { return a + b; }")
+            .parse_statements()
+            .unwrap();
+        interpreter.execute(program).unwrap();
+
+        for (source, expected) in [
+            ("nil", "nil"),
+            ("true", "true"),
+            ("false", "false"),
+            ("1", "1"),
+            ("2.50", "2.5"),
+            ("-3", "-3"),
+            ("0 / 0", "nan"),
+            ("\"text\"", "text"),
+            ("\"a\\0b\"", "a\\0b"),
+            ("[1, \"a\", nil]", "[1, a, nil]"),
+            ("{a: true}", "{a: true}"),
+            ("add", "<fn add>"),
+            ("clock", "<native fn>"),
+            ("Point", "Point"),
+            ("Point()", "Point instance"),
+        ] {
+            assert_eq!(evaluate(&mut interpreter, source).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn call_function_from_the_host() {
+        let mut interpreter = Interpreter::new();
+        let program = Parser::new(
+            "var greeting = \"Hello\"; fun greet(name) { return greeting + \", \" + name + \"!\"; }",
+        )
+        .parse_statements()
+        .unwrap();
+        interpreter.execute(program).unwrap();
+
+        let value = interpreter
+            .call_function("greet", vec![Value::string("Lox")])
+            .unwrap();
+        assert!(value.equals(&Value::string("Hello, Lox!")));
+        assert!(interpreter
+            .call_function("max", vec![Value::Number(1.0), Value::Number(3.0)])
+            .unwrap()
+            .equals(&Value::Number(3.0)));
+
+        let mut message = |name: &str, args: Vec<Value>| {
+            interpreter
+                .call_function(name, args)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(message("greet", vec![]), "Expected 1 arguments but got 0.");
+        assert_eq!(
+            message("greeting", vec![]),
+            "'greeting' is a string, not a function."
+        );
+        assert_eq!(message("missing", vec![]), "Undefined variable 'missing'.");
+    }
+
+    #[test]
+    fn value_conversions() {
+        assert!(Value::from(1.5).equals(&Value::Number(1.5)));
+        assert!(Value::from(true).equals(&Value::Boolean(true)));
+        assert!(Value::from("lox").equals(&Value::string("lox")));
+        assert!(Value::from(String::from("lox")).equals(&Value::string("lox")));
+
+        assert_eq!(f64::try_from(Value::Number(2.0)).unwrap(), 2.0);
+        assert!(bool::try_from(Value::Boolean(false)).is_ok_and(|b| !b));
+        assert_eq!(String::try_from(Value::string("x")).unwrap(), "x");
+
+        let message = |err: InterpreterError| err.to_string();
+        assert_eq!(
+            message(f64::try_from(Value::Nil).unwrap_err()),
+            "Operand must be a number."
+        );
+        assert_eq!(
+            message(bool::try_from(Value::Number(0.0)).unwrap_err()),
+            "Operand must be a boolean."
+        );
+        assert_eq!(
+            message(String::try_from(Value::Boolean(true)).unwrap_err()),
+            "Operand must be a string."
+        );
+    }
+
+    #[test]
+    fn not_callable_errors_carry_their_line() {
+        let mut interpreter = Interpreter::new();
+        let program = Parser::new("var x = 5;\n\nx();")
+            .parse_statements()
+            .unwrap();
+        let err = interpreter.execute(program).unwrap_err();
+
+        assert!(matches!(err, InterpreterError::NotCallable { line: 3, .. }));
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(
+            err.to_string(),
+            "Can only call functions and classes, got number."
+        );
+        assert_eq!(err.exit_code(), ExitCode::RunTimeError);
+    }
+
+    #[test]
+    fn string_indexing() {
+        let mut interpreter = Interpreter::new();
+        assert!(evaluate(&mut interpreter, "\"hello\"[1]").equals(&Value::string("e")));
+        assert!(evaluate(&mut interpreter, "\"héllo\"[1]").equals(&Value::string("é")));
+        assert!(evaluate(&mut interpreter, "\"héllo\"[-1]").equals(&Value::string("o")));
+
+        let mut message = |source: &str| interpreter.execute_expr(source).unwrap_err().to_string();
+        assert_eq!(message("\"abc\"[3]"), "Index out of range.");
+        assert_eq!(message("\"abc\"[-4]"), "Index out of range.");
+        assert_eq!(message("\"abc\"[0.5]"), "String index must be an integer.");
+        assert_eq!(message("\"abc\"[\"a\"]"), "String index must be a number.");
+    }
+
+    #[test]
+    fn malformed_class_members_are_errors() {
+        let class = Statement::Class {
+            name: "Odd".to_string(),
+            superclass: None,
+            methods: vec![Statement::Empty],
+            statics: Vec::new(),
+            line: 1,
+        };
+
+        let err = Interpreter::new().visit_stmt(&class).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Class 'Odd' has a member that is not a method."
+        );
     }
-}
 
-fn is_equal(a: &Value, b: &Value) -> bool {
-    match (a, b) {
-        (Value::Nil, Value::Nil) => true,
-        (Value::Nil, _) | (_, Value::Nil) => false,
-        (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
-        (Value::Number(n1), Value::Number(n2)) => n1 == n2,
-        (Value::String(s1), Value::String(s2)) => s1 == s2,
-        _ => false,
+    #[test]
+    fn string_pseudo_properties() {
+        let mut interpreter = Interpreter::new();
+        assert!(evaluate(&mut interpreter, "\"abc\".length").equals(&Value::Number(3.0)));
+        assert!(evaluate(&mut interpreter, "\"héllo\".length").equals(&Value::Number(5.0)));
+        assert!(evaluate(&mut interpreter, "\"abc\".upper()").equals(&Value::string("ABC")));
+        assert!(evaluate(&mut interpreter, "\"ABC\".lower()").equals(&Value::string("abc")));
+
+        let err = interpreter.execute_expr("\"abc\".size").unwrap_err();
+        assert_eq!(err.to_string(), "Undefined property 'size'.");
+    }
+
+    #[test]
+    fn nil_coalescing() {
+        let mut interpreter = Interpreter::new();
+
+        assert!(evaluate(&mut interpreter, "false ?? 1").equals(&Value::Boolean(false)));
+        assert!(evaluate(&mut interpreter, "nil ?? 1").equals(&Value::Number(1.0)));
+        assert!(evaluate(&mut interpreter, "nil ?? nil ?? \"c\"").equals(&Value::string("c")));
+        assert!(evaluate(&mut interpreter, "nil ?? false or 2").equals(&Value::Number(2.0)));
+        // The right side is never evaluated when the left is not nil.
+        assert!(evaluate(&mut interpreter, "0 ?? undefined").equals(&Value::Number(0.0)));
+    }
+
+    #[test]
+    fn binary_operand_errors() {
+        let mut interpreter = Interpreter::new();
+        let mut message = |source: &str| interpreter.execute_expr(source).unwrap_err().to_string();
+
+        assert_eq!(message("true < 1"), "Operands must be numbers.");
+        assert_eq!(message("\"a\" * 2"), "Operands must be numbers.");
+        assert_eq!(
+            message("1 + \"x\""),
+            "Operands must be two numbers or two strings."
+        );
+    }
+
+    #[test]
+    fn bound_method_this_is_the_instance() {
+        let mut interpreter = Interpreter::new();
+        let program =
+            Parser::new("class Pet { name() { return this.name; } } var a = Pet(); var b = Pet();")
+                .parse_statements()
+                .unwrap();
+        interpreter.execute(program).unwrap();
+
+        let (Value::Instance(a), Value::Instance(b)) = (
+            interpreter.global("a").unwrap(),
+            interpreter.global("b").unwrap(),
+        ) else {
+            panic!("expected instances");
+        };
+        a.set("name", Value::string("Ace"));
+        b.set("name", Value::string("Bo"));
+
+        let method = a.find_method("name").unwrap();
+        let name = method
+            .bind(b.clone())
+            .call(&mut interpreter, vec![])
+            .unwrap();
+        assert!(name.equals(&Value::string("Bo")));
+        let name = method.bind(a).call(&mut interpreter, vec![]).unwrap();
+        assert!(name.equals(&Value::string("Ace")));
+    }
+
+    #[test]
+    fn native_closure_captures_state() {
+        let counter = Rc::new(RefCell::new(0_i64));
+        let mut interpreter = Interpreter::new();
+        let captured = counter.clone();
+        interpreter.define_native("tick", 0, move |_| {
+            *captured.borrow_mut() += 1;
+            Ok(Value::Number(*captured.borrow() as f64))
+        });
+
+        evaluate(&mut interpreter, "tick()");
+        let value = evaluate(&mut interpreter, "tick()");
+
+        assert_eq!(*counter.borrow(), 2);
+        assert!(value.equals(&Value::Number(2.0)));
+    }
+
+    #[test]
+    fn globals_snapshot_after_run() {
+        let mut interpreter = Interpreter::new();
+        let statements = Parser::new("var answer = 42; { var answer = 1; }")
+            .parse_statements()
+            .unwrap();
+        for statement in &statements {
+            interpreter.visit_stmt(statement).unwrap();
+        }
+
+        let globals = interpreter.globals();
+        assert!(globals["answer"].equals(&Value::Number(42.0)));
+        assert!(globals.contains_key("clock"));
+    }
+
+    #[test]
+    fn logical_operators_return_operands() {
+        let mut interpreter = Interpreter::new();
+        let cases = [
+            ("nil or \"x\"", "x"),
+            ("0 or 1", "0"),
+            ("false or nil", "nil"),
+            ("\"\" or 2", ""),
+            ("true or undefined", "true"),
+            ("nil and undefined", "nil"),
+            ("false and 1", "false"),
+            ("0 and \"y\"", "y"),
+            ("\"a\" and \"b\"", "b"),
+            ("1 and nil", "nil"),
+            ("nil or false or 3", "3"),
+            ("1 and 2 or 3", "2"),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(
+                evaluate(&mut interpreter, source).to_string(),
+                expected,
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn loop_iteration_limit() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_loop_limit(Some(1000));
+        let statements = Parser::new("var i = 0; while (true) i = i + 1;")
+            .parse_statements()
+            .unwrap();
+
+        let error = statements
+            .iter()
+            .try_for_each(|statement| interpreter.visit_stmt(statement))
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            InterpreterError::Message(ref message, ExitCode::RunTimeError)
+                if message == "Loop iteration limit exceeded."
+        ));
+        assert!(interpreter.globals()["i"].equals(&Value::Number(1000.0)));
+    }
+
+    #[test]
+    fn environment_distinguishes_nil_from_undefined() {
+        let mut environment = Environment::new();
+        environment.define("x", Value::Nil);
+
+        assert!(matches!(environment.get("x"), Ok(Value::Nil)));
+        assert_eq!(
+            environment.get("y").unwrap_err(),
+            EnvError::Undefined("y".to_string())
+        );
+        assert_eq!(
+            environment.assign("y", Value::Nil),
+            Err(EnvError::Undefined("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn errors_map_to_exit_codes() {
+        let cases = [
+            (
+                InterpreterError::Message("x".to_string(), ExitCode::CompilerError),
+                ExitCode::CompilerError,
+            ),
+            (
+                InterpreterError::Message("x".to_string(), ExitCode::RunTimeError),
+                ExitCode::RunTimeError,
+            ),
+            (
+                InterpreterError::UndefinedVariable("x".to_string()),
+                ExitCode::RunTimeError,
+            ),
+            (
+                InterpreterError::ReturnError(Value::Nil),
+                ExitCode::RunTimeError,
+            ),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code);
+        }
+        assert_eq!(i32::from(ExitCode::CompilerError), 65);
+        assert_eq!(i32::from(ExitCode::RunTimeError), 70);
+    }
+
+    #[test]
+    fn value_accessors() {
+        assert_eq!(Value::number(1.5).as_number().unwrap(), 1.5);
+        assert_eq!(Value::string("lox").as_string().unwrap(), "lox");
+        assert!(Value::boolean(true).as_bool().unwrap());
+
+        let message = |error: InterpreterError| error.to_string();
+        assert_eq!(
+            message(Value::string("1").as_number().unwrap_err()),
+            "Operand must be a number."
+        );
+        assert_eq!(
+            message(Value::Nil.as_string().unwrap_err()),
+            "Operand must be a string."
+        );
+        assert_eq!(
+            message(Value::number(0.0).as_bool().unwrap_err()),
+            "Operand must be a boolean."
+        );
+        assert_eq!(Value::list(vec![Value::Nil]).to_string(), "[nil]");
+    }
+
+    #[test]
+    fn equality_contract() {
+        let mut interpreter = Interpreter::new();
+        let cases = [
+            ("0/0 == 0/0", "false"),
+            ("0/0 != 0/0", "true"),
+            ("nil == false", "false"),
+            ("nil != false", "true"),
+            ("nil == nil", "true"),
+            ("1 == \"1\"", "false"),
+            ("1 != \"1\"", "true"),
+            ("0 == false", "false"),
+            ("\"\" == nil", "false"),
+            ("1 == 1.0", "true"),
+            ("\"a\" == \"a\"", "true"),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(
+                evaluate(&mut interpreter, source).to_string(),
+                expected,
+                "{source}"
+            );
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trace_logs_statements_in_order() {
+        let buffer = SharedBuffer::default();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_trace(true);
+        interpreter.set_trace_output(buffer.clone());
+
+        let statements = Parser::new("var a = 1;\nif (a)\n  a = 2;\n;\nprint a, \"done\";")
+            .parse_statements()
+            .unwrap();
+        interpreter.execute(statements).unwrap();
+
+        let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(
+            trace.lines().collect::<Vec<_>>(),
+            [
+                "[trace] [line 1] var a = 1;",
+                "[trace] [line 2] if (a)",
+                "[trace] [line 3] a = 2;",
+                "[trace] [line 5] print a, \"done\";",
+            ]
+        );
     }
 }
@@ -7,6 +7,8 @@ mod function_trait;
 mod interpreter;
 mod lox_class;
 mod lox_instance;
+mod natives;
+mod optimizer;
 mod parser;
 mod resolver;
 mod tokenizer;
@@ -18,7 +20,35 @@ pub use function_trait::*;
 pub use interpreter::*;
 pub use lox_class::*;
 pub use lox_instance::*;
+pub use optimizer::*;
 pub use parser::*;
 pub use resolver::*;
 pub use tokenizer::*;
 pub use visit::*;
+
+/// Runs `source` through the whole pipeline, returning the first error
+/// instead of exiting the process.
+pub fn interpret(source: &str) -> Result<()> {
+    let statements = Parser::new(source).parse_statements()?;
+    Interpreter::run(statements)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpret, InterpreterError, ParserError};
+
+    #[test]
+    fn interpret_returns_errors() {
+        assert!(interpret("var a = 1; print a;").is_ok());
+
+        let err = interpret("print (1;").unwrap_err();
+        assert!(err.downcast_ref::<ParserError>().is_some());
+
+        let err = interpret("return 1;").unwrap_err();
+        assert!(err.downcast_ref::<InterpreterError>().is_some());
+
+        let err = interpret("print -\"a\";").unwrap_err();
+        assert_eq!(err.to_string(), "Operand must be a number.");
+    }
+}
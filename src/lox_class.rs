@@ -1,12 +1,15 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{BoundMethod, Callable, LoxFunction, LoxInstance, Value};
+use crate::{Callable, LoxFunction, LoxInstance, Value};
 
 #[derive(Debug, Clone)]
 pub struct LoxClass {
     pub name: String,
     pub superclass: Option<Rc<LoxClass>>,
     pub methods: Rc<RefCell<HashMap<String, LoxFunction>>>,
+    pub statics: Rc<RefCell<HashMap<String, Value>>>,
+    // Own and inherited methods, flattened on first lookup.
+    method_cache: Rc<RefCell<Option<HashMap<String, LoxFunction>>>>,
 }
 
 impl LoxClass {
@@ -15,18 +18,50 @@ impl LoxClass {
             name,
             superclass,
             methods: Rc::new(RefCell::new(HashMap::new())),
+            statics: Rc::new(RefCell::new(HashMap::new())),
+            method_cache: Rc::new(RefCell::new(None)),
         }
     }
 
+    pub fn define_static(&self, name: String, value: Value) {
+        self.statics.borrow_mut().insert(name, value);
+    }
+
+    /// Class-level constants, inherited from superclasses.
+    pub fn get_static(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.statics.borrow().get(name) {
+            return Some(value.clone());
+        }
+        self.superclass.as_ref().and_then(|s| s.get_static(name))
+    }
+
     pub fn create_method(&self, name: String, method: LoxFunction) {
         self.methods.borrow_mut().insert(name, method);
+        self.method_cache.borrow_mut().take();
     }
 
     pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
-        if let Some(m) = self.methods.borrow().get(name) {
-            return Some(m.clone());
+        let mut cache = self.method_cache.borrow_mut();
+        cache
+            .get_or_insert_with(|| self.all_methods())
+            .get(name)
+            .cloned()
+    }
+
+    fn all_methods(&self) -> HashMap<String, LoxFunction> {
+        let mut methods = match &self.superclass {
+            Some(superclass) => {
+                let mut cache = superclass.method_cache.borrow_mut();
+                cache
+                    .get_or_insert_with(|| superclass.all_methods())
+                    .clone()
+            }
+            None => HashMap::new(),
+        };
+        for (name, method) in self.methods.borrow().iter() {
+            methods.insert(name.clone(), method.clone());
         }
-        self.superclass.as_ref().and_then(|s| s.find_method(name))
+        methods
     }
 
     pub fn name(&self) -> String {
@@ -43,12 +78,9 @@ impl Callable for LoxClass {
         let instance = LoxInstance::new(Rc::new(self.clone()));
         let instance_rc = Rc::new(instance);
         if let Some(initializer) = self.find_method("init") {
-            let bound = BoundMethod {
-                function: Rc::new(initializer),
-                instance: instance_rc.clone(),
-            };
-            let bound_rc: Rc<dyn Callable> = Rc::new(bound);
-            bound_rc.call(interpreter, args)?;
+            initializer
+                .bind(instance_rc.clone())
+                .call(interpreter, args)?;
         }
 
         Ok(Value::Instance(instance_rc))
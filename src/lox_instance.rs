@@ -1,6 +1,6 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{BoundMethod, InterpreterError, LoxClass, LoxFunction, Value};
+use crate::{InterpreterError, LoxClass, LoxFunction, Value};
 
 #[derive(Debug, Clone)]
 pub struct LoxInstance {
@@ -16,16 +16,21 @@ impl LoxInstance {
         }
     }
 
+    /// A new instance of the same class with a copy of this one's fields.
+    pub fn shallow_clone(&self) -> Self {
+        Self {
+            class: self.class.clone(),
+            fields: Rc::new(RefCell::new(self.fields.borrow().clone())),
+        }
+    }
+
     pub fn get(&self, name: &str) -> Result<Value, InterpreterError> {
         if let Some(value) = self.fields.borrow().get(name).cloned() {
             return Ok(value);
         }
 
         if let Some(method) = self.find_method(name) {
-            return Ok(Value::Function(Rc::new(BoundMethod {
-                function: Rc::new(method),
-                instance: Rc::new(self.clone()),
-            })));
+            return Ok(Value::Function(Rc::new(method.bind(Rc::new(self.clone())))));
         }
 
         Err(InterpreterError::Message(
@@ -42,7 +47,7 @@ impl LoxInstance {
         format!("{} instance", self.class.name.clone())
     }
 
-    fn find_method(&self, name: &str) -> Option<LoxFunction> {
+    pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
         self.class.find_method(name)
     }
 }
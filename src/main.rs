@@ -1,6 +1,11 @@
 use clap::{Parser, Subcommand};
-use codecrafters_interpreter::{IlligalType, Interpreter, InterpreterError, Lexer, TokenKind};
-use std::{path::PathBuf, process};
+use codecrafters_interpreter::{
+    tokens_to_json, IlligalType, Interpreter, InterpreterError, Lexer, Token, TokenKind,
+};
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
 
 #[derive(Debug, Parser)]
 struct Cli {
@@ -10,22 +15,69 @@ struct Cli {
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    Tokenize { filename: PathBuf },
-    Parse { filename: PathBuf },
-    Evaluate { filename: PathBuf },
-    Run { filename: PathBuf },
+    Tokenize {
+        filename: PathBuf,
+        #[arg(long)]
+        json: bool,
+        /// Print the token and error counts to stderr.
+        #[arg(long)]
+        summary: bool,
+    },
+    Parse {
+        filename: PathBuf,
+    },
+    Evaluate {
+        filename: PathBuf,
+    },
+    Run {
+        filename: PathBuf,
+        #[arg(long)]
+        trace: bool,
+        /// Stop reporting parse errors after this many.
+        #[arg(long, default_value_t = codecrafters_interpreter::DEFAULT_MAX_ERRORS)]
+        max_errors: usize,
+    },
+}
+
+impl Command {
+    fn filename(&self) -> &Path {
+        match self {
+            Command::Tokenize { filename, .. }
+            | Command::Parse { filename }
+            | Command::Evaluate { filename }
+            | Command::Run { filename, .. } => filename,
+        }
+    }
 }
 
 fn main() -> codecrafters_interpreter::Result<()> {
     let args = Cli::parse();
+    let Some(file_content) = read_source(args.command.filename())? else {
+        eprintln!("[line 1] Error: source file is not valid UTF-8");
+        process::exit(65);
+    };
+
     match args.command {
-        Command::Tokenize { filename } => {
-            let file_content = std::fs::read_to_string(filename)?;
+        Command::Tokenize { json, summary, .. } => {
             let lexer = Lexer::new(&file_content);
             let mut has_error_token = false;
 
             let tokens: Vec<_> = lexer.collect();
 
+            if json {
+                println!("{}", tokens_to_json(&tokens));
+                if summary {
+                    print_summary(&tokens);
+                }
+                if tokens
+                    .iter()
+                    .any(|token| matches!(token.kind, TokenKind::Illegal(_)))
+                {
+                    process::exit(65);
+                }
+                return Ok(());
+            }
+
             for token in &tokens {
                 if let TokenKind::Illegal(ty) = &token.kind {
                     has_error_token = true;
@@ -39,6 +91,15 @@ fn main() -> codecrafters_interpreter::Result<()> {
                                 token.line, token.literal
                             );
                         }
+                        IlligalType::InvalidCharLiteral => {
+                            eprintln!(
+                                "[line {}] Error: Character literal must contain exactly one character.",
+                                token.line
+                            );
+                        }
+                        IlligalType::LiteralTooLong => {
+                            eprintln!("[line {}] Error: Literal is too long.", token.line);
+                        }
                     }
                 } else {
                     println!("{}", token);
@@ -46,13 +107,15 @@ fn main() -> codecrafters_interpreter::Result<()> {
             }
 
             println!("EOF  null");
+            if summary {
+                print_summary(&tokens);
+            }
 
             if has_error_token {
                 process::exit(65);
             }
         }
-        Command::Parse { filename } => {
-            let file_content = std::fs::read_to_string(filename)?;
+        Command::Parse { .. } => {
             let mut parser = codecrafters_interpreter::Parser::new(&file_content);
 
             match parser.parse() {
@@ -65,48 +128,34 @@ fn main() -> codecrafters_interpreter::Result<()> {
                 }
             }
         }
-        Command::Evaluate { filename } => {
-            let file_content = std::fs::read_to_string(filename)?;
-            let mut parser = codecrafters_interpreter::Parser::new(&file_content);
+        Command::Evaluate { .. } => match Interpreter::new().execute_expr(&file_content) {
+            Ok(value) => {
+                println!("{value}");
+            }
+            Err(err) => runtime_error(&err),
+        },
 
-            let mut interpreter = Interpreter::new();
-            match parser.parse() {
-                Ok(expr) => match interpreter.evaluate(&expr) {
-                    Ok(value) => {
-                        println!("{value}");
+        Command::Run {
+            filename,
+            trace,
+            max_errors,
+        } => {
+            let mut parser =
+                codecrafters_interpreter::Parser::new(&file_content).with_max_errors(max_errors);
+
+            match parser.parse_all() {
+                Ok(stmt) => {
+                    let mut interpreter = Interpreter::new();
+                    interpreter.set_trace(trace);
+                    interpreter.set_source_path(&filename);
+                    if let Err(err) = interpreter.execute(stmt) {
+                        runtime_error(&err);
                     }
-                    Err(err) => {
+                }
+                Err(errors) => {
+                    for err in errors {
                         eprintln!("{err}");
-                        process::exit(70);
                     }
-                },
-                Err(err) => {
-                    eprintln!("{err}");
-                    process::exit(65);
-                }
-            }
-        }
-
-        Command::Run { filename } => {
-            let file_content = std::fs::read_to_string(filename)?;
-            let mut parser = codecrafters_interpreter::Parser::new(&file_content);
-
-            match parser.parse_statements() {
-                Ok(stmt) => match Interpreter::run(stmt) {
-                    Ok(_) => {}
-                    Err(err) => match err {
-                        InterpreterError::Message(s, code) => {
-                            eprintln!("{s}");
-                            process::exit(code.into());
-                        }
-                        _ => {
-                            eprintln!("{err}");
-                            process::exit(70);
-                        }
-                    },
-                },
-                Err(err) => {
-                    eprintln!("{err}");
                     process::exit(65);
                 }
             }
@@ -114,3 +163,32 @@ fn main() -> codecrafters_interpreter::Result<()> {
     }
     Ok(())
 }
+
+/// Reports `err` as reference Lox does, with its line on the next line when known.
+fn runtime_error(err: &InterpreterError) -> ! {
+    eprintln!("{err}");
+    if let Some(line) = err.line() {
+        eprintln!("[line {line}]");
+    }
+    process::exit(err.exit_code().into())
+}
+
+fn print_summary(tokens: &[Token]) {
+    let errors = tokens
+        .iter()
+        .filter(|token| matches!(token.kind, TokenKind::Illegal(_)))
+        .count();
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    eprintln!(
+        "# {} token{}, {} error{}",
+        tokens.len(),
+        plural(tokens.len()),
+        errors,
+        plural(errors)
+    );
+}
+
+/// The source at `path`, or `None` if it isn't valid UTF-8.
+fn read_source(path: &Path) -> codecrafters_interpreter::Result<Option<String>> {
+    Ok(String::from_utf8(std::fs::read(path)?).ok())
+}
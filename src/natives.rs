@@ -0,0 +1,346 @@
+use std::{
+    cell::RefCell,
+    io::Write,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use indexmap::IndexMap;
+
+use crate::{Callable, ExitCode, Interpreter, InterpreterError, Value};
+
+pub(crate) fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+    interpreter.define_native("clock_ms", 0, clock_ms);
+    interpreter.define_native("sleep", 1, sleep);
+    interpreter.define_native("type", 1, type_of);
+    interpreter.define_interpreter_native("write", 1..=1, write);
+    interpreter.define_native("keys", 1, keys);
+    interpreter.define_native("values", 1, values);
+    interpreter.define_native("clone", 1, clone);
+    interpreter.define_variadic_native("assert", 1..=2, assert);
+    interpreter.define_native("floor", 1, floor);
+    interpreter.define_native("ceil", 1, ceil);
+    interpreter.define_native("round", 1, round);
+    interpreter.define_native("abs", 1, abs);
+    interpreter.define_native("sqrt", 1, sqrt);
+    interpreter.define_native("floordiv", 2, floordiv);
+    interpreter.define_native("pow", 2, pow);
+    interpreter.define_native("log", 1, log);
+    interpreter.define_native("log10", 1, log10);
+    interpreter.define_native("sin", 1, sin);
+    interpreter.define_native("cos", 1, cos);
+    interpreter.define_native("tan", 1, tan);
+    interpreter.define_native("format_number", 2, format_number);
+    interpreter.define_variadic_native("min", 1..=usize::MAX, min);
+    interpreter.define_variadic_native("max", 1..=usize::MAX, max);
+    interpreter.define_native("upper", 1, upper);
+    interpreter.define_native("lower", 1, lower);
+    interpreter.define_native("trim", 1, trim);
+    interpreter.define_native("split", 2, split);
+    interpreter.define_native("join", 2, join);
+    interpreter.define_native("contains", 2, contains);
+    interpreter.define_native("index_of", 2, index_of);
+    interpreter.define_interpreter_native("global", 1..=1, global);
+    interpreter.define_interpreter_native("map", 2..=2, map_list);
+    interpreter.define_interpreter_native("filter", 2..=2, filter_list);
+    interpreter.define_interpreter_native("each", 2..=2, each_list);
+}
+
+fn clock(_args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let start_time = SystemTime::now();
+    let since_the_epoch = start_time
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    Ok(Value::number(since_the_epoch.as_secs_f64()))
+}
+
+fn clock_ms(_args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let since_the_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    Ok(Value::number(since_the_epoch.as_millis() as f64))
+}
+
+fn sleep(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let ms = args[0].as_number()?;
+    // `as u64` would turn NaN into 0 and infinity into a sleep that never ends.
+    if !ms.is_finite() || ms < 0.0 {
+        return Err(InterpreterError::Message(
+            "Sleep duration must be a finite, non-negative number.".to_string(),
+            ExitCode::RunTimeError,
+        ));
+    }
+    std::thread::sleep(Duration::from_millis(ms as u64));
+    Ok(Value::Nil)
+}
+
+fn global(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, InterpreterError> {
+    interpreter.global(args[0].as_string()?)
+}
+
+fn map_list(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let function = callback(&args[1])?;
+    let mut results = Vec::new();
+    for element in list(&args[0])? {
+        results.push(function.call(interpreter, vec![element])?);
+    }
+    Ok(Value::list(results))
+}
+
+fn filter_list(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let function = callback(&args[1])?;
+    let mut kept = Vec::new();
+    for element in list(&args[0])? {
+        if function
+            .call(interpreter, vec![element.clone()])?
+            .is_truthy()
+        {
+            kept.push(element);
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+fn each_list(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let function = callback(&args[1])?;
+    for element in list(&args[0])? {
+        function.call(interpreter, vec![element])?;
+    }
+    Ok(Value::Nil)
+}
+
+fn type_of(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::string(args[0].type_name()))
+}
+
+// Formats like `print`, so a `toString` method is honoured.
+fn write(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let text = interpreter.stringify(&args[0])?;
+    let mut stdout = std::io::stdout();
+    write!(stdout, "{text}")
+        .and_then(|_| stdout.flush())
+        .map_err(|e| InterpreterError::Message(e.to_string(), ExitCode::RunTimeError))?;
+    Ok(Value::Nil)
+}
+
+// Both follow the map's insertion order.
+fn keys(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let keys = map(&args[0])?
+        .borrow()
+        .keys()
+        .cloned()
+        .map(Value::String)
+        .collect();
+    Ok(Value::list(keys))
+}
+
+fn values(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let values = map(&args[0])?.borrow().values().cloned().collect();
+    Ok(Value::list(values))
+}
+
+fn clone(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    match &args[0] {
+        Value::Instance(instance) => Ok(Value::Instance(Rc::new(instance.shallow_clone()))),
+        _ => Err(InterpreterError::Message(
+            "Can only clone instances.".to_string(),
+            ExitCode::RunTimeError,
+        )),
+    }
+}
+
+fn assert(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    if args[0].is_truthy() {
+        return Ok(Value::Nil);
+    }
+
+    let message = match args.get(1) {
+        Some(message) => message.to_string(),
+        None => "Assertion failed.".to_string(),
+    };
+    Err(InterpreterError::Message(message, ExitCode::RunTimeError))
+}
+
+fn floor(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.floor()))
+}
+
+fn ceil(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.ceil()))
+}
+
+fn round(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.round()))
+}
+
+fn abs(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.abs()))
+}
+
+fn sqrt(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let n = args[0].as_number()?;
+    if n < 0.0 {
+        return Err(InterpreterError::Message(
+            "Can't take the square root of a negative number.".to_string(),
+            ExitCode::RunTimeError,
+        ));
+    }
+    Ok(Value::number(n.sqrt()))
+}
+
+/// Integer division rounding toward negative infinity. A native rather than a
+/// `//` operator, which would clash with comments.
+fn floordiv(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let dividend = args[0].as_number()?;
+    let divisor = args[1].as_number()?;
+    if divisor == 0.0 {
+        return Err(InterpreterError::Message(
+            "Division by zero.".to_string(),
+            ExitCode::RunTimeError,
+        ));
+    }
+    Ok(Value::number((dividend / divisor).floor()))
+}
+
+fn pow(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(
+        args[0].as_number()?.powf(args[1].as_number()?),
+    ))
+}
+
+fn positive(value: &Value) -> Result<f64, InterpreterError> {
+    let n = value.as_number()?;
+    if n <= 0.0 {
+        return Err(InterpreterError::Message(
+            "Can't take the logarithm of a non-positive number.".to_string(),
+            ExitCode::RunTimeError,
+        ));
+    }
+    Ok(n)
+}
+
+fn log(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(positive(&args[0])?.ln()))
+}
+
+fn log10(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(positive(&args[0])?.log10()))
+}
+
+fn sin(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.sin()))
+}
+
+fn cos(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.cos()))
+}
+
+fn tan(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::number(args[0].as_number()?.tan()))
+}
+
+/// `n` as a string with exactly `decimals` places, clamped to 0..=20.
+fn format_number(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let n = args[0].as_number()?;
+    let decimals = args[1].as_number()?.clamp(0.0, 20.0) as usize;
+    Ok(Value::string(format!("{n:.decimals$}")))
+}
+
+fn min(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let mut result = f64::INFINITY;
+    for arg in &args {
+        result = result.min(arg.as_number()?);
+    }
+    Ok(Value::number(result))
+}
+
+fn max(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let mut result = f64::NEG_INFINITY;
+    for arg in &args {
+        result = result.max(arg.as_number()?);
+    }
+    Ok(Value::number(result))
+}
+
+fn upper(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::string(args[0].as_string()?.to_uppercase()))
+}
+
+fn lower(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::string(args[0].as_string()?.to_lowercase()))
+}
+
+fn trim(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    Ok(Value::string(args[0].as_string()?.trim()))
+}
+
+fn split(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let s = args[0].as_string()?;
+    let separator = args[1].as_string()?;
+    let parts = if separator.is_empty() {
+        s.chars().map(Value::string).collect()
+    } else {
+        s.split(separator).map(Value::string).collect()
+    };
+    Ok(Value::list(parts))
+}
+
+fn join(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let items = list(&args[0])?;
+    let separator = args[1].as_string()?;
+    let parts: Vec<String> = items.iter().map(Value::to_string).collect();
+    Ok(Value::string(parts.join(separator)))
+}
+
+fn contains(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let haystack = args[0].as_string()?;
+    let needle = args[1].as_string()?;
+    Ok(Value::boolean(haystack.contains(needle)))
+}
+
+fn index_of(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let haystack = args[0].as_string()?;
+    let needle = args[1].as_string()?;
+    // Report the position in characters rather than bytes.
+    let index = match haystack.find(needle) {
+        Some(byte_index) => haystack[..byte_index].chars().count() as f64,
+        None => -1.0,
+    };
+    Ok(Value::number(index))
+}
+
+/// A snapshot of a list's elements, so callbacks may modify the list.
+fn list(value: &Value) -> Result<Vec<Value>, InterpreterError> {
+    match value {
+        Value::List(list) => Ok(list.borrow().clone()),
+        _ => Err(InterpreterError::Message(
+            "Argument must be a list.".to_string(),
+            ExitCode::RunTimeError,
+        )),
+    }
+}
+
+fn callback(value: &Value) -> Result<Rc<dyn Callable>, InterpreterError> {
+    match value {
+        Value::Function(function) if function.arity_range().contains(&1) => Ok(function.clone()),
+        Value::Function(_) => Err(InterpreterError::Message(
+            "Callback must take one argument.".to_string(),
+            ExitCode::RunTimeError,
+        )),
+        _ => Err(InterpreterError::Message(
+            "Argument must be a function.".to_string(),
+            ExitCode::RunTimeError,
+        )),
+    }
+}
+
+fn map(value: &Value) -> Result<&Rc<RefCell<IndexMap<String, Value>>>, InterpreterError> {
+    match value {
+        Value::Map(map) => Ok(map),
+        _ => Err(InterpreterError::Message(
+            "Argument must be a map.".to_string(),
+            ExitCode::RunTimeError,
+        )),
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{Expression, Literal, Statement, TokenKind};
+
+/// Replaces constant subexpressions with their values. Anything that could
+/// fail at runtime, such as division by zero, is left for the interpreter.
+pub fn constant_fold(stmts: &mut [Statement]) {
+    for stmt in stmts.iter_mut() {
+        fold_stmt(stmt);
+    }
+}
+
+fn fold_stmt(stmt: &mut Statement) {
+    match stmt {
+        Statement::Empty
+        | Statement::Import { .. }
+        | Statement::Break { .. }
+        | Statement::Continue { .. } => {}
+        Statement::Expr { expression, .. } => fold_expr(expression),
+        Statement::Print { expressions, .. } => {
+            for expr in expressions.iter_mut() {
+                fold_expr(expr);
+            }
+        }
+        Statement::Block { statements, .. } => constant_fold(statements),
+        Statement::Class {
+            methods, statics, ..
+        } => {
+            constant_fold(methods);
+            for (_, value) in statics.iter_mut() {
+                fold_expr(value);
+            }
+        }
+        Statement::Var { initializer, .. } => {
+            if let Some(expr) = initializer {
+                fold_expr(expr);
+            }
+        }
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            fold_expr(condition);
+            fold_stmt(then_branch);
+            let mut next = else_branch.as_deref_mut();
+            while let Some(statement) = next {
+                match statement {
+                    Statement::If {
+                        condition,
+                        then_branch,
+                        else_branch,
+                        ..
+                    } => {
+                        fold_expr(condition);
+                        fold_stmt(then_branch);
+                        next = else_branch.as_deref_mut();
+                    }
+                    other => {
+                        fold_stmt(other);
+                        next = None;
+                    }
+                }
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            fold_expr(condition);
+            fold_stmt(body);
+        }
+        Statement::DoWhile {
+            body, condition, ..
+        } => {
+            fold_stmt(body);
+            fold_expr(condition);
+        }
+        Statement::For {
+            initialize,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            if let Some(init) = initialize {
+                fold_stmt(init);
+            }
+            if let Some(con) = condition {
+                fold_expr(con);
+            }
+            if let Some(inc) = increment {
+                fold_expr(inc);
+            }
+            fold_stmt(body);
+        }
+        Statement::ForIn { iterable, body, .. } => {
+            fold_expr(iterable);
+            fold_stmt(body);
+        }
+        Statement::Switch {
+            discriminant,
+            cases,
+            default,
+            ..
+        } => {
+            fold_expr(discriminant);
+            for (value, body) in cases.iter_mut() {
+                fold_expr(value);
+                constant_fold(body);
+            }
+            if let Some(body) = default {
+                constant_fold(body);
+            }
+        }
+        Statement::Function { body, .. } => constant_fold(body),
+        Statement::Return { value, .. } => {
+            if let Some(expr) = value {
+                fold_expr(expr);
+            }
+        }
+    }
+}
+
+fn fold_expr(expr: &mut Expression) {
+    match expr {
+        Expression::Literal(_)
+        | Expression::Variable { .. }
+        | Expression::Postfix { .. }
+        | Expression::This { .. }
+        | Expression::Super { .. } => {}
+        Expression::Group(inner) => {
+            fold_expr(inner);
+            if let Expression::Literal(literal) = inner.as_mut() {
+                *expr = Expression::Literal(literal.clone());
+            }
+        }
+        Expression::Unary {
+            operator,
+            expression,
+        } => {
+            fold_expr(expression);
+            if let Expression::Literal(literal) = expression.as_ref() {
+                if let Some(folded) = fold_unary(*operator, literal) {
+                    *expr = Expression::Literal(folded);
+                }
+            }
+        }
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            fold_expr(left);
+            fold_expr(right);
+            if let (Expression::Literal(l), Expression::Literal(r)) =
+                (left.as_ref(), right.as_ref())
+            {
+                if let Some(folded) = fold_binary(l, *operator, r) {
+                    *expr = Expression::Literal(folded);
+                }
+            }
+        }
+        Expression::Logical { left, right, .. } => {
+            fold_expr(left);
+            fold_expr(right);
+        }
+        Expression::Assign { value, .. } => fold_expr(value),
+        Expression::List(elements) => {
+            for element in elements.iter_mut() {
+                fold_expr(element);
+            }
+        }
+        Expression::Map(entries) => {
+            for (_, value) in entries.iter_mut() {
+                fold_expr(value);
+            }
+        }
+        Expression::Call { callee, args, .. } => {
+            fold_expr(callee);
+            for arg in args.iter_mut() {
+                fold_expr(arg);
+            }
+        }
+        Expression::Set { object, value, .. } => {
+            fold_expr(object);
+            fold_expr(value);
+        }
+        Expression::Get { object, .. } => fold_expr(object),
+        Expression::Index { object, index } => {
+            fold_expr(object);
+            fold_expr(index);
+        }
+        Expression::Lambda { body, .. } => constant_fold(body),
+        Expression::Block { statements, value } => {
+            constant_fold(statements);
+            fold_expr(value);
+        }
+    }
+}
+
+fn fold_unary(operator: TokenKind, literal: &Literal) -> Option<Literal> {
+    match (operator, literal) {
+        (TokenKind::Minus, Literal::Number(n)) => Some(Literal::Number(-n)),
+        (TokenKind::Bang, Literal::Nil) | (TokenKind::Bang, Literal::Boolean(false)) => {
+            Some(Literal::Boolean(true))
+        }
+        (TokenKind::Bang, _) => Some(Literal::Boolean(false)),
+        _ => None,
+    }
+}
+
+fn fold_binary(left: &Literal, operator: TokenKind, right: &Literal) -> Option<Literal> {
+    match (left, operator, right) {
+        (Literal::Number(l), TokenKind::Plus, Literal::Number(r)) => Some(Literal::Number(l + r)),
+        (Literal::Number(l), TokenKind::Minus, Literal::Number(r)) => Some(Literal::Number(l - r)),
+        (Literal::Number(l), TokenKind::Star, Literal::Number(r)) => Some(Literal::Number(l * r)),
+        (Literal::Number(l), TokenKind::Slash, Literal::Number(r)) if *r != 0.0 => {
+            Some(Literal::Number(l / r))
+        }
+        (Literal::Number(l), TokenKind::Greater, Literal::Number(r)) => {
+            Some(Literal::Boolean(l > r))
+        }
+        (Literal::Number(l), TokenKind::GreaterEqual, Literal::Number(r)) => {
+            Some(Literal::Boolean(l >= r))
+        }
+        (Literal::Number(l), TokenKind::Less, Literal::Number(r)) => Some(Literal::Boolean(l < r)),
+        (Literal::Number(l), TokenKind::LessEqual, Literal::Number(r)) => {
+            Some(Literal::Boolean(l <= r))
+        }
+        (Literal::String(l), TokenKind::Plus, Literal::String(r)) => {
+            Some(Literal::String(format!("{l}{r}")))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constant_fold, Expression, Literal, Parser, Statement};
+
+    fn fold(source: &str) -> Expression {
+        let mut statements = Parser::new(source).parse_statements().unwrap();
+        constant_fold(&mut statements);
+        match statements.remove(0) {
+            Statement::Print {
+                mut expressions, ..
+            } => expressions.remove(0),
+            other => panic!("expected a print statement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn folds_arithmetic() {
+        assert!(matches!(
+            fold("print 1 + 2 * 3;"),
+            Expression::Literal(Literal::Number(n)) if n == 7.0
+        ));
+        assert!(matches!(
+            fold("print -(4 - 1) >= (2);"),
+            Expression::Literal(Literal::Boolean(false))
+        ));
+        assert!(matches!(
+            fold("print \"a\" + \"b\";"),
+            Expression::Literal(Literal::String(ref s)) if s == "ab"
+        ));
+    }
+
+    #[test]
+    fn leaves_runtime_failures_alone() {
+        assert!(matches!(fold("print 1 / 0;"), Expression::Binary { .. }));
+        assert!(matches!(
+            fold("print 1 + \"a\";"),
+            Expression::Binary { .. }
+        ));
+        assert!(matches!(fold("print -\"a\";"), Expression::Unary { .. }));
+    }
+}
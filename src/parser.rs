@@ -1,13 +1,24 @@
-use std::iter::Peekable;
+use std::{iter::Peekable, vec};
 
-use crate::{ast::Expression, Lexer, Statement, Token, TokenKind};
+use crate::{ast::Expression, IlligalType, Lexer, Statement, Token, TokenKind};
 
 #[derive(Debug)]
 pub enum ParserError {
     Message(String),
-    UnexpectedEof { line: usize },
-    UnexpectedToken { line: usize, token: String },
-    InvalidAssignmentTarget { line: usize, token: String },
+    UnexpectedEof {
+        line: usize,
+    },
+    UnexpectedToken {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    InvalidAssignmentTarget {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    TooManyErrors,
 }
 
 impl std::fmt::Display for ParserError {
@@ -16,10 +27,10 @@ impl std::fmt::Display for ParserError {
             ParserError::UnexpectedEof { line } => {
                 write!(f, "[line {}] Error: Unexpected EOF", line)
             }
-            ParserError::UnexpectedToken { line, token } => {
+            ParserError::UnexpectedToken { line, token, .. } => {
                 write!(f, "[line {line}] Error at '{token}': Expect expression.")
             }
-            ParserError::InvalidAssignmentTarget { line, token } => {
+            ParserError::InvalidAssignmentTarget { line, token, .. } => {
                 write!(
                     f,
                     "[line {line}] Error at '{token}': Invalid assignment target."
@@ -27,26 +38,95 @@ impl std::fmt::Display for ParserError {
             }
 
             ParserError::Message(s) => write!(f, "{s}"),
+            ParserError::TooManyErrors => write!(f, "Too many errors; stopping."),
         }
     }
 }
 
+impl ParserError {
+    /// The error message followed by the offending source line, with a caret
+    /// under the token's column. Errors without a position render as usual.
+    pub fn render_with_source(&self, source: &str) -> String {
+        let (line, column) = match self {
+            ParserError::UnexpectedToken { line, column, .. }
+            | ParserError::InvalidAssignmentTarget { line, column, .. } => (*line, *column),
+            _ => return self.to_string(),
+        };
+        let Some(text) = source.lines().nth(line.saturating_sub(1)) else {
+            return self.to_string();
+        };
+        // Keep tabs so the caret lines up however the terminal renders them.
+        let padding: String = text
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!("{self}\n{text}\n{padding}^")
+    }
+}
+
 impl std::error::Error for ParserError {}
 
-pub struct Parser<'input> {
-    tokens: Peekable<Lexer<'input>>,
+/// How many errors `parse_all` collects before giving up.
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+pub struct Parser {
+    tokens: Peekable<vec::IntoIter<Token>>,
     had_error: bool,
+    max_errors: usize,
+    // Line of the last consumed token, where running out of input is reported.
+    previous_line: usize,
 }
 
-impl<'input> Parser<'input> {
-    pub fn new(input: &'input str) -> Self {
+impl Parser {
+    pub fn new(input: &str) -> Self {
+        Self::from_tokens(Lexer::new(input))
+    }
+
+    /// Parses an already lexed token stream, which should not include an EOF token.
+    pub fn from_tokens(tokens: impl IntoIterator<Item = Token>) -> Self {
         Parser {
-            tokens: Lexer::new(input).peekable(),
+            tokens: tokens
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_iter()
+                .peekable(),
             had_error: false,
+            max_errors: DEFAULT_MAX_ERRORS,
+            previous_line: 1,
         }
     }
 
+    /// Whether `source` stops partway through a bracketed construct or a
+    /// string, so a REPL should read another line before parsing.
+    pub fn needs_more_input(source: &str) -> bool {
+        let mut depth: isize = 0;
+        for token in Lexer::new(source) {
+            match token.kind {
+                TokenKind::LeftParen | TokenKind::LeftBrace | TokenKind::LeftBracket => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBrace | TokenKind::RightBracket => {
+                    depth -= 1
+                }
+                TokenKind::Illegal(IlligalType::UnterminatedString) => return true,
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
     pub fn parse_statements(&mut self) -> Result<Vec<Statement>, ParserError> {
+        self.parse_all()
+            .map_err(|errors| errors.into_iter().next().unwrap())
+    }
+
+    /// Parses every statement, recovering after each error. Stops once
+    /// `max_errors` have been collected, ending the list with `TooManyErrors`.
+    pub fn parse_all(&mut self) -> Result<Vec<Statement>, Vec<ParserError>> {
         let mut statements = Vec::new();
         let mut errors = Vec::new();
 
@@ -56,6 +136,10 @@ impl<'input> Parser<'input> {
                 Err(e) => {
                     errors.push(e);
                     self.had_error = true;
+                    if errors.len() >= self.max_errors {
+                        errors.push(ParserError::TooManyErrors);
+                        break;
+                    }
                     self.synchronize();
                 }
             }
@@ -64,34 +148,50 @@ impl<'input> Parser<'input> {
         if errors.is_empty() {
             Ok(statements)
         } else {
-            Err(errors.into_iter().next().unwrap())
+            Err(errors)
         }
     }
 
     fn statement(&mut self) -> Result<Statement, ParserError> {
-        if let Some(token) = self.peek() {
-            match token.kind {
+        if let Some(kind) = self.peek().map(|t| t.kind) {
+            match kind {
+                TokenKind::Semi => {
+                    self.advance().unwrap(); // Consume ';'
+                    Ok(Statement::Empty)
+                }
                 TokenKind::Print => self.print_statement(),
+                TokenKind::Import => self.import_statement(),
                 TokenKind::Var => self.declaration(),
                 TokenKind::LeftBrace => self.block(),
                 TokenKind::If => self.if_statement(),
+                TokenKind::Unless => self.unless_statement(),
                 TokenKind::While => self.while_statement(),
+                TokenKind::Do => self.do_while_statement(),
                 TokenKind::For => self.for_statement(),
-                TokenKind::Fun => {
+                TokenKind::When => self.when_statement(),
+                TokenKind::Fun
+                    if self.peek_next().map(|t| t.kind) != Some(TokenKind::LeftParen) =>
+                {
                     self.advance().unwrap();
                     self.function()
                 }
                 TokenKind::Return => self.return_statement(),
+                TokenKind::Break | TokenKind::Continue => self.jump_statement(),
+                TokenKind::Identifier
+                    if self.peek_next().map(|t| t.kind) == Some(TokenKind::Colon) =>
+                {
+                    self.labeled_statement()
+                }
                 TokenKind::Class => self.class_declaration(),
                 _ => self.expr_statement(),
             }
         } else {
-            Err(ParserError::UnexpectedEof { line: 1 })
+            Err(self.unexpected_eof())
         }
     }
 
     fn declaration(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'var'
+        let line = self.advance().unwrap().line; // Consume 'var'
         let variable = self.consume(TokenKind::Identifier)?;
         let mut initializer: Option<Expression> = None;
 
@@ -105,11 +205,12 @@ impl<'input> Parser<'input> {
         Ok(Statement::Var {
             name: variable.literal,
             initializer,
+            line,
         })
     }
 
     fn class_declaration(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'class'
+        let line = self.advance().unwrap().line; // Consume 'class'
         let name = self.consume(TokenKind::Identifier)?.literal;
         let superclass = if self.peek().map(|t| t.kind) == Some(TokenKind::Less) {
             self.advance().unwrap(); // Consume '<'
@@ -119,13 +220,20 @@ impl<'input> Parser<'input> {
         };
         self.consume(TokenKind::LeftBrace)?;
         let mut methods = vec![];
+        let mut statics = vec![];
         while let Some(token) = self.peek() {
-            if token.kind == TokenKind::RightBrace {
-                break;
+            match token.kind {
+                TokenKind::RightBrace => break,
+                TokenKind::Var => {
+                    self.advance().unwrap(); // Consume 'var'
+                    let name = self.consume(TokenKind::Identifier)?.literal;
+                    self.consume(TokenKind::Equal)?;
+                    let value = self.expression()?;
+                    self.consume(TokenKind::Semi)?;
+                    statics.push((name, value));
+                }
+                _ => methods.push(self.function()?),
             }
-
-            let function = self.function()?;
-            methods.push(function);
         }
 
         self.consume(TokenKind::RightBrace)?;
@@ -134,11 +242,13 @@ impl<'input> Parser<'input> {
             name,
             superclass,
             methods,
+            statics,
+            line,
         })
     }
 
     fn return_statement(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'return'
+        let line = self.advance().unwrap().line; // Consume 'return'
         let mut value = None;
         if self.peek().map(|t| t.kind) != Some(TokenKind::Semi) {
             value = Some(self.expression()?);
@@ -146,11 +256,22 @@ impl<'input> Parser<'input> {
 
         self.consume(TokenKind::Semi)?;
 
-        Ok(Statement::Return { value })
+        Ok(Statement::Return { value, line })
     }
 
     fn function(&mut self) -> Result<Statement, ParserError> {
-        let function_name = self.consume(TokenKind::Identifier)?.literal;
+        let function_name = self.consume(TokenKind::Identifier)?;
+        let (params, body) = self.function_body()?;
+
+        Ok(Statement::Function {
+            name: function_name.literal,
+            params,
+            body,
+            line: function_name.line,
+        })
+    }
+
+    fn function_body(&mut self) -> Result<(Vec<String>, Vec<Statement>), ParserError> {
         self.consume(TokenKind::LeftParen)?;
         let mut params = vec![];
         if self.peek().map(|t| t.kind) != Some(TokenKind::RightParen) {
@@ -167,36 +288,56 @@ impl<'input> Parser<'input> {
                     break;
                 }
                 self.advance().unwrap(); // Consume ','
+                if self.peek().map(|t| t.kind) == Some(TokenKind::RightParen) {
+                    break;
+                }
             }
         }
         self.consume(TokenKind::RightParen)?;
 
-        let body = match self.block()? {
-            Statement::Block(v) => v,
-            _ => unreachable!(),
-        };
+        let body = self.block_statements()?;
 
-        Ok(Statement::Function {
-            name: function_name,
-            params,
-            body,
-        })
+        Ok((params, body))
     }
 
     fn print_statement(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'print'
-        let expr = self.expression()?;
+        let line = self.advance().unwrap().line; // Consume 'print'
+        let mut expressions = vec![self.expression()?];
+        while self.peek().map(|t| t.kind) == Some(TokenKind::Comma) {
+            self.advance().unwrap(); // Consume ','
+            expressions.push(self.expression()?);
+        }
+        self.consume(TokenKind::Semi)?;
+        Ok(Statement::Print { expressions, line })
+    }
+
+    fn import_statement(&mut self) -> Result<Statement, ParserError> {
+        let line = self.advance().unwrap().line; // Consume 'import'
+        let path = self.consume(TokenKind::String)?;
         self.consume(TokenKind::Semi)?;
-        Ok(Statement::Print(expr))
+        Ok(Statement::Import {
+            path: path.literal,
+            line,
+        })
     }
 
     fn expr_statement(&mut self) -> Result<Statement, ParserError> {
-        let expr = self.expression()?;
+        let line = self.current_line();
+        let expression = self.expression()?;
         self.consume(TokenKind::Semi)?;
-        Ok(Statement::Expr(expr))
+        Ok(Statement::Expr { expression, line })
     }
 
     fn block(&mut self) -> Result<Statement, ParserError> {
+        let line = self.current_line();
+        Ok(Statement::Block {
+            statements: self.block_statements()?,
+            scoped: true,
+            line,
+        })
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Statement>, ParserError> {
         self.advance().unwrap(); // Consume '{'
         let mut blocks = Vec::new();
 
@@ -208,32 +349,76 @@ impl<'input> Parser<'input> {
         }
 
         self.consume(TokenKind::RightBrace)?;
-        Ok(Statement::Block(blocks))
+        Ok(blocks)
     }
 
     fn if_statement(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'if'
-        self.consume(TokenKind::LeftParen)?;
-        let condition = self.expression()?;
-        self.consume(TokenKind::RightParen)?;
+        let line = self.advance().unwrap().line; // Consume 'if'
+        let (condition, then_branch) = self.if_branch()?;
+        self.finish_if(condition, then_branch, line)
+    }
 
-        let then_branch = Box::new(self.statement()?);
-        let mut else_branch = None;
+    /// `unless (c) s` is `if (!(c)) s`.
+    fn unless_statement(&mut self) -> Result<Statement, ParserError> {
+        let line = self.advance().unwrap().line; // Consume 'unless'
+        let (condition, then_branch) = self.if_branch()?;
+        let condition = Expression::Unary {
+            operator: TokenKind::Bang,
+            expression: Box::new(Expression::Group(Box::new(condition))),
+        };
+        self.finish_if(condition, then_branch, line)
+    }
 
-        if self.peek().map(|t| t.kind) == Some(TokenKind::Else) {
+    /// Parses any `else` branches and builds the `if`.
+    fn finish_if(
+        &mut self,
+        condition: Expression,
+        then_branch: Box<Statement>,
+        line: usize,
+    ) -> Result<Statement, ParserError> {
+        // `else if` chains are collected in a loop rather than by recursion,
+        // so long chains don't grow the stack.
+        let mut else_ifs = Vec::new();
+        let mut else_branch = None;
+        while self.peek().map(|t| t.kind) == Some(TokenKind::Else) {
             self.advance().unwrap(); // Consume 'else'
-            else_branch = Some(Box::new(self.statement()?));
+            if self.peek().map(|t| t.kind) == Some(TokenKind::If) {
+                let line = self.advance().unwrap().line; // Consume 'if'
+                let (condition, then_branch) = self.if_branch()?;
+                else_ifs.push((condition, then_branch, line));
+            } else {
+                else_branch = Some(Box::new(self.statement()?));
+                break;
+            }
+        }
+
+        for (condition, then_branch, line) in else_ifs.into_iter().rev() {
+            else_branch = Some(Box::new(Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+            }));
         }
 
         Ok(Statement::If {
             condition,
             then_branch,
             else_branch,
+            line,
         })
     }
 
+    /// The `(condition) statement` part of an `if`.
+    fn if_branch(&mut self) -> Result<(Expression, Box<Statement>), ParserError> {
+        self.consume(TokenKind::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(TokenKind::RightParen)?;
+        Ok((condition, Box::new(self.statement()?)))
+    }
+
     fn while_statement(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'while'
+        let line = self.advance().unwrap().line; // Consume 'while'
         self.consume(TokenKind::LeftParen)?;
         let condition = self.expression()?;
         self.consume(TokenKind::RightParen)?;
@@ -242,13 +427,81 @@ impl<'input> Parser<'input> {
         Ok(Statement::While {
             condition: Box::new(condition),
             body,
+            label: None,
+            line,
+        })
+    }
+
+    fn labeled_statement(&mut self) -> Result<Statement, ParserError> {
+        let name = self.consume(TokenKind::Identifier)?.literal;
+        self.consume(TokenKind::Colon)?;
+
+        let mut statement = match self.peek().map(|t| t.kind) {
+            Some(TokenKind::While | TokenKind::Do | TokenKind::For) => self.statement()?,
+            _ => {
+                return Err(ParserError::Message(format!(
+                    "Expect a loop after label '{name}'."
+                )))
+            }
+        };
+        match &mut statement {
+            Statement::While { label, .. }
+            | Statement::DoWhile { label, .. }
+            | Statement::For { label, .. }
+            | Statement::ForIn { label, .. } => *label = Some(name),
+            _ => {
+                return Err(ParserError::Message(format!(
+                    "Expect a loop after label '{name}'."
+                )))
+            }
+        }
+        Ok(statement)
+    }
+
+    fn jump_statement(&mut self) -> Result<Statement, ParserError> {
+        let keyword = self.advance().unwrap(); // Consume 'break' or 'continue'
+        let line = keyword.line;
+        let label = if self.peek().map(|t| t.kind) == Some(TokenKind::Identifier) {
+            Some(self.advance().unwrap().literal)
+        } else {
+            None
+        };
+        self.consume(TokenKind::Semi)?;
+
+        if keyword.kind == TokenKind::Break {
+            Ok(Statement::Break { label, line })
+        } else {
+            Ok(Statement::Continue { label, line })
+        }
+    }
+
+    fn do_while_statement(&mut self) -> Result<Statement, ParserError> {
+        let line = self.advance().unwrap().line; // Consume 'do'
+        let body = Box::new(self.statement()?);
+        self.consume(TokenKind::While)?;
+        self.consume(TokenKind::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(TokenKind::RightParen)?;
+        self.consume(TokenKind::Semi)?;
+
+        Ok(Statement::DoWhile {
+            body,
+            condition,
+            label: None,
+            line,
         })
     }
 
     fn for_statement(&mut self) -> Result<Statement, ParserError> {
-        self.advance().unwrap(); // Consume 'for'
+        let line = self.advance().unwrap().line; // Consume 'for'
         self.consume(TokenKind::LeftParen)?;
 
+        if self.peek().map(|t| t.kind) == Some(TokenKind::Identifier)
+            && self.peek_next().map(|t| t.kind) == Some(TokenKind::In)
+        {
+            return self.for_in_statement(line);
+        }
+
         let initialize = if self.peek().map(|t| t.kind) != Some(TokenKind::Semi) {
             Some(Box::new(
                 if self.peek().map(|t| t.kind) == Some(TokenKind::Var) {
@@ -276,7 +529,7 @@ impl<'input> Parser<'input> {
         };
         self.consume(TokenKind::RightParen)?;
         let body = if self.peek().map(|t| t.kind) == Some(TokenKind::Var) {
-            self.advance().unwrap();
+            let line = self.advance().unwrap().line;
             let variable = self.consume(TokenKind::Identifier)?;
             self.consume(TokenKind::Equal)?;
             let initial = Some(self.expression()?);
@@ -284,6 +537,7 @@ impl<'input> Parser<'input> {
             Statement::Var {
                 name: variable.literal,
                 initializer: initial,
+                line,
             }
         } else {
             self.statement()?
@@ -294,6 +548,75 @@ impl<'input> Parser<'input> {
             condition,
             increment,
             body: Box::new(body),
+            label: None,
+            line,
+        })
+    }
+
+    fn when_statement(&mut self) -> Result<Statement, ParserError> {
+        let line = self.advance().unwrap().line; // Consume 'when'
+        self.consume(TokenKind::LeftParen)?;
+        let discriminant = self.expression()?;
+        self.consume(TokenKind::RightParen)?;
+        self.consume(TokenKind::LeftBrace)?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        loop {
+            match self.peek().map(|t| t.kind) {
+                Some(TokenKind::Case) => {
+                    self.advance().unwrap(); // Consume 'case'
+                    let value = self.expression()?;
+                    self.consume(TokenKind::Colon)?;
+                    cases.push((value, self.case_body()?));
+                }
+                Some(TokenKind::Default) => {
+                    self.advance().unwrap(); // Consume 'default'
+                    if default.is_some() {
+                        return Err(ParserError::Message(
+                            "Already a default case in this when.".to_string(),
+                        ));
+                    }
+                    self.consume(TokenKind::Colon)?;
+                    default = Some(self.case_body()?);
+                }
+                _ => break,
+            }
+        }
+        self.consume(TokenKind::RightBrace)?;
+
+        Ok(Statement::Switch {
+            discriminant,
+            cases,
+            default,
+            line,
+        })
+    }
+
+    fn case_body(&mut self) -> Result<Vec<Statement>, ParserError> {
+        let mut statements = Vec::new();
+        while !matches!(
+            self.peek().map(|t| t.kind),
+            None | Some(TokenKind::Case | TokenKind::Default | TokenKind::RightBrace)
+        ) {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn for_in_statement(&mut self, line: usize) -> Result<Statement, ParserError> {
+        let name = self.consume(TokenKind::Identifier)?.literal;
+        self.consume(TokenKind::In)?;
+        let iterable = self.expression()?;
+        self.consume(TokenKind::RightParen)?;
+        let body = self.statement()?;
+
+        Ok(Statement::ForIn {
+            name,
+            iterable,
+            body: Box::new(body),
+            label: None,
+            line,
         })
     }
 
@@ -330,17 +653,71 @@ impl<'input> Parser<'input> {
 
             return Err(ParserError::InvalidAssignmentTarget {
                 line: token.line,
+                column: token.column,
                 token: token.literal,
             });
         }
 
+        if self.peek().map(|t| t.kind) == Some(TokenKind::PlusEqual) {
+            let token = self.advance().unwrap();
+            let value = self.assignment()?;
+
+            // Desugars to `x = (x += y)`, where the binary `+=` extends lists
+            // in place and otherwise behaves like `+`. A field target becomes
+            // `o.f = (o.f += y)`, so `o` is evaluated twice.
+            return match expr {
+                Expression::Variable { name, resolved: _ } => Ok(Expression::Assign {
+                    name: name.clone(),
+                    value: Box::new(Expression::Binary {
+                        left: Box::new(Expression::Variable {
+                            name,
+                            resolved: None,
+                        }),
+                        operator: TokenKind::PlusEqual,
+                        right: Box::new(value),
+                    }),
+                    resolved: None,
+                }),
+                Expression::Get { object, name } => Ok(Expression::Set {
+                    object: object.clone(),
+                    property: name.clone(),
+                    value: Box::new(Expression::Binary {
+                        left: Box::new(Expression::Get { object, name }),
+                        operator: TokenKind::PlusEqual,
+                        right: Box::new(value),
+                    }),
+                }),
+                _ => Err(ParserError::InvalidAssignmentTarget {
+                    line: token.line,
+                    column: token.column,
+                    token: token.literal,
+                }),
+            };
+        }
+
         Ok(expr)
     }
 
     fn or_expression(&mut self) -> Result<Expression, ParserError> {
-        let mut expr = self.and_expression()?;
+        let mut expr = self.coalesce_expression()?;
 
         while self.peek().map(|t| t.kind) == Some(TokenKind::Or) {
+            let operator = self.advance().unwrap();
+            let right = self.coalesce_expression()?;
+            expr = Expression::Logical {
+                left: Box::new(expr),
+                operator: operator.kind,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn coalesce_expression(&mut self) -> Result<Expression, ParserError> {
+        let mut expr = self.and_expression()?;
+
+        while self.peek().map(|t| t.kind) == Some(TokenKind::QuestionQuestion) {
             let operator = self.advance().unwrap();
             let right = self.and_expression()?;
             expr = Expression::Logical {
@@ -462,7 +839,72 @@ impl<'input> Parser<'input> {
                 });
             }
         }
-        self.call()
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expression, ParserError> {
+        let expr = self.call()?;
+
+        if let Some(operator) = self.postfix_operator() {
+            let token = self.advance().unwrap();
+            self.advance().unwrap(); // Consume the second '+' or '-'
+            return match expr {
+                Expression::Variable { name, resolved: _ } => Ok(Expression::Postfix {
+                    name,
+                    operator,
+                    resolved: None,
+                }),
+                _ => Err(ParserError::InvalidAssignmentTarget {
+                    line: token.line,
+                    column: token.column,
+                    token: token.literal.repeat(2),
+                }),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// `++` and `--` are lexed as two tokens, since `a--b` and `1--1` are
+    /// subtractions. They form a postfix operator when the two are adjacent,
+    /// unless it is `--` followed by something `a - -x` could negate. There
+    /// is no unary `+`, so `++` never reads as an addition.
+    fn postfix_operator(&mut self) -> Option<TokenKind> {
+        let first = self.peek()?.clone();
+        let operator = match first.kind {
+            TokenKind::Plus => TokenKind::PlusPlus,
+            TokenKind::Minus => TokenKind::MinusMinus,
+            _ => return None,
+        };
+        let second = self.peek_nth(1)?;
+        if second.kind != first.kind
+            || second.line != first.line
+            || second.column != first.column + 1
+        {
+            return None;
+        }
+        if operator == TokenKind::PlusPlus {
+            return Some(operator);
+        }
+        match self.peek_nth(2).map(|t| t.kind) {
+            Some(
+                TokenKind::Number(_)
+                | TokenKind::String
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Nil
+                | TokenKind::Identifier
+                | TokenKind::This
+                | TokenKind::Super
+                | TokenKind::Fun
+                | TokenKind::LeftParen
+                | TokenKind::LeftBracket
+                | TokenKind::LeftBrace
+                | TokenKind::Bang
+                | TokenKind::Minus,
+            ) => None,
+            _ => Some(operator),
+        }
     }
 
     fn call(&mut self) -> Result<Expression, ParserError> {
@@ -470,8 +912,8 @@ impl<'input> Parser<'input> {
 
         loop {
             if self.peek().map(|t| t.kind) == Some(TokenKind::LeftParen) {
-                self.advance().unwrap(); // Consume '('
-                expr = self.finish_call(expr)?;
+                let paren = self.advance().unwrap(); // Consume '('
+                expr = self.finish_call(expr, paren.line)?;
             } else if self.peek().map(|t| t.kind) == Some(TokenKind::Dot) {
                 self.advance().unwrap(); // Consume '.'
                 let ident = self.consume(TokenKind::Identifier)?;
@@ -479,6 +921,14 @@ impl<'input> Parser<'input> {
                     object: Box::new(expr),
                     name: ident.literal,
                 }
+            } else if self.peek().map(|t| t.kind) == Some(TokenKind::LeftBracket) {
+                self.advance().unwrap(); // Consume '['
+                let index = self.expression()?;
+                self.consume(TokenKind::RightBracket)?;
+                expr = Expression::Index {
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                }
             } else {
                 break;
             }
@@ -486,7 +936,7 @@ impl<'input> Parser<'input> {
         Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expression) -> Result<Expression, ParserError> {
+    fn finish_call(&mut self, callee: Expression, line: usize) -> Result<Expression, ParserError> {
         let mut args = Vec::new();
         if self.peek().map(|t| t.kind) != Some(TokenKind::RightParen) {
             loop {
@@ -500,6 +950,9 @@ impl<'input> Parser<'input> {
                     break;
                 }
                 self.advance().unwrap(); // Consume ','
+                if self.peek().map(|t| t.kind) == Some(TokenKind::RightParen) {
+                    break;
+                }
             }
         }
 
@@ -508,13 +961,14 @@ impl<'input> Parser<'input> {
         Ok(Expression::Call {
             callee: Box::new(callee),
             args,
+            line,
         })
     }
 
     fn primary(&mut self) -> Result<Expression, ParserError> {
         let token = match self.advance() {
             Some(token) => token,
-            None => return Err(ParserError::UnexpectedEof { line: 1 }),
+            None => return Err(self.unexpected_eof()),
         };
 
         match token.kind {
@@ -534,6 +988,23 @@ impl<'input> Parser<'input> {
                 self.consume(TokenKind::RightParen)?;
                 Ok(Expression::Group(Box::new(expression)))
             }
+            TokenKind::LeftBracket => self.list(),
+            TokenKind::Fun => {
+                let (params, body) = self.function_body()?;
+                Ok(Expression::Lambda { params, body })
+            }
+            // `{}` and `{ key: ...` are map literals; any other `{` in
+            // expression position starts a block expression.
+            TokenKind::LeftBrace
+                if self.peek().map(|t| t.kind) == Some(TokenKind::RightBrace)
+                    || (matches!(
+                        self.peek().map(|t| t.kind),
+                        Some(TokenKind::String | TokenKind::Identifier)
+                    ) && self.peek_next().map(|t| t.kind) == Some(TokenKind::Colon)) =>
+            {
+                self.map()
+            }
+            TokenKind::LeftBrace => self.block_expression(),
             TokenKind::This => Ok(Expression::This { resolved: None }),
             TokenKind::Super => {
                 self.consume(TokenKind::Dot)?;
@@ -545,11 +1016,108 @@ impl<'input> Parser<'input> {
             }
             _ => Err(ParserError::UnexpectedToken {
                 line: token.line,
+                column: token.column,
                 token: token.literal,
             }),
         }
     }
 
+    fn list(&mut self) -> Result<Expression, ParserError> {
+        let mut elements = Vec::new();
+        while self.peek().map(|t| t.kind) != Some(TokenKind::RightBracket) {
+            elements.push(self.expression()?);
+            if self.peek().map(|t| t.kind) != Some(TokenKind::Comma) {
+                break;
+            }
+            self.advance().unwrap(); // Consume ','
+        }
+        self.consume(TokenKind::RightBracket)?;
+
+        Ok(Expression::List(elements))
+    }
+
+    fn map(&mut self) -> Result<Expression, ParserError> {
+        let mut entries = Vec::new();
+        while self.peek().map(|t| t.kind) != Some(TokenKind::RightBrace) {
+            let key = match self.advance() {
+                Some(token) if matches!(token.kind, TokenKind::String | TokenKind::Identifier) => {
+                    token.literal
+                }
+                Some(token) => {
+                    return Err(ParserError::UnexpectedToken {
+                        line: token.line,
+                        column: token.column,
+                        token: token.literal,
+                    })
+                }
+                None => return Err(self.unexpected_eof()),
+            };
+            self.consume(TokenKind::Colon)?;
+            entries.push((key, self.expression()?));
+            if self.peek().map(|t| t.kind) != Some(TokenKind::Comma) {
+                break;
+            }
+            self.advance().unwrap(); // Consume ','
+        }
+        self.consume(TokenKind::RightBrace)?;
+
+        Ok(Expression::Map(entries))
+    }
+
+    /// The rest of `{ stmt; ... expr }` after the opening brace. Statements
+    /// run first; the trailing expression, with no `;`, is the value.
+    fn block_expression(&mut self) -> Result<Expression, ParserError> {
+        let mut statements = Vec::new();
+        loop {
+            if self.starts_statement() {
+                statements.push(self.statement()?);
+                continue;
+            }
+            let line = self.current_line();
+            let expr = self.expression()?;
+            if self.peek().map(|t| t.kind) == Some(TokenKind::Semi) {
+                self.advance().unwrap(); // Consume ';'
+                statements.push(Statement::Expr {
+                    expression: expr,
+                    line,
+                });
+                continue;
+            }
+            self.consume(TokenKind::RightBrace)?;
+            return Ok(Expression::Block {
+                statements,
+                value: Box::new(expr),
+            });
+        }
+    }
+
+    fn starts_statement(&mut self) -> bool {
+        match self.peek().map(|t| t.kind) {
+            Some(
+                TokenKind::Semi
+                | TokenKind::Print
+                | TokenKind::Import
+                | TokenKind::Var
+                | TokenKind::LeftBrace
+                | TokenKind::If
+                | TokenKind::Unless
+                | TokenKind::While
+                | TokenKind::Do
+                | TokenKind::For
+                | TokenKind::When
+                | TokenKind::Return
+                | TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Class,
+            ) => true,
+            Some(TokenKind::Fun) => self.peek_next().map(|t| t.kind) != Some(TokenKind::LeftParen),
+            Some(TokenKind::Identifier) => {
+                self.peek_next().map(|t| t.kind) == Some(TokenKind::Colon)
+            }
+            _ => false,
+        }
+    }
+
     fn synchronize(&mut self) {
         while let Some(token) = self.peek() {
             if token.kind == TokenKind::Semi {
@@ -560,10 +1128,14 @@ impl<'input> Parser<'input> {
             if matches!(
                 token.kind,
                 TokenKind::Print
+                    | TokenKind::Import
                     | TokenKind::Var
                     | TokenKind::LeftBrace
                     | TokenKind::If
+                    | TokenKind::Unless
                     | TokenKind::While
+                    | TokenKind::Do
+                    | TokenKind::When
                     | TokenKind::For
                     | TokenKind::Return
                     | TokenKind::Fun
@@ -584,25 +1156,234 @@ impl<'input> Parser<'input> {
         self.tokens.peek()
     }
 
+    /// The line of the next token, or of the last one at the end of input.
+    fn current_line(&mut self) -> usize {
+        match self.peek() {
+            Some(token) => token.line,
+            None => self.previous_line,
+        }
+    }
+
+    fn peek_next(&self) -> Option<Token> {
+        self.peek_nth(1)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<Token> {
+        self.tokens.clone().nth(n)
+    }
+
     fn consume(&mut self, expected: TokenKind) -> Result<Token, ParserError> {
         match self.advance() {
             Some(token) if token.kind == expected => Ok(token),
             Some(token) => Err(ParserError::UnexpectedToken {
                 line: token.line,
+                column: token.column,
                 token: token.literal,
             }),
-            None => {
-                let line = self.tokens.peek().map(|t| t.line).unwrap_or(1);
-                Err(ParserError::UnexpectedEof { line })
-            }
+            None => Err(self.unexpected_eof()),
+        }
+    }
+
+    fn unexpected_eof(&self) -> ParserError {
+        ParserError::UnexpectedEof {
+            line: self.previous_line,
         }
     }
 
     fn advance(&mut self) -> Option<Token> {
-        self.tokens.next()
+        let token = self.tokens.next()?;
+        self.previous_line = token.line;
+        Some(token)
     }
 
     pub fn had_error(&self) -> bool {
         self.had_error
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, Literal, Parser, ParserError, Statement, Token, TokenKind};
+
+    #[test]
+    fn trailing_comma_in_arguments() {
+        let expr = Parser::new("f(1,)").parse().unwrap();
+        match expr {
+            Expression::Call { args, .. } => assert_eq!(args.len(), 1),
+            other => panic!("expected a call, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn trailing_comma_in_parameters() {
+        let statements = Parser::new("fun g(a,) {}").parse_statements().unwrap();
+        match &statements[..] {
+            [Statement::Function { params, .. }] => assert_eq!(params, &["a".to_string()]),
+            other => panic!("expected a function, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn eof_inside_a_map_literal_reports_the_last_line() {
+        for source in [
+            "{\n  a: 1,\n  b: 2,\n",
+            "{\n  a: 1,\n  b",
+            "{\n  a: 1,\n  b:",
+        ] {
+            let err = Parser::new(source).parse().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "[line 3] Error: Unexpected EOF",
+                "{source:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn postfix_requires_a_variable() {
+        let expr = Parser::new("i++").parse().unwrap();
+        assert!(matches!(expr, Expression::Postfix { .. }));
+
+        let err = Parser::new("1++").parse().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[line 1] Error at '++': Invalid assignment target."
+        );
+
+        // Followed by an operand, the pair is a binary and a unary operator.
+        for source in ["1--1", "a--b", "a - -b", "i- -"] {
+            let expr = Parser::new(source).parse();
+            assert!(!matches!(expr, Ok(Expression::Postfix { .. })), "{source}");
+        }
+        assert_eq!(
+            Parser::new("1--1").parse().unwrap().to_string(),
+            "(- 1.0 (- 1.0))"
+        );
+    }
+
+    #[test]
+    fn for_in_over_list_literal() {
+        let statements = Parser::new("for (x in [1, 2, 3,]) print x;")
+            .parse_statements()
+            .unwrap();
+        match &statements[..] {
+            [Statement::ForIn {
+                name,
+                iterable: Expression::List(elements),
+                ..
+            }] => {
+                assert_eq!(name, "x");
+                assert_eq!(elements.len(), 3);
+            }
+            other => panic!("expected a for-in loop, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stray_semicolons_are_empty_statements() {
+        let statements = Parser::new(";;;").parse_statements().unwrap();
+        assert_eq!(statements.len(), 3);
+        assert!(statements.iter().all(|s| matches!(s, Statement::Empty)));
+    }
+
+    #[test]
+    fn parse_from_tokens() {
+        let token = |kind, literal: &str, column| Token {
+            kind,
+            literal: literal.to_string(),
+            line: 1,
+            column,
+        };
+        let tokens = vec![
+            token(TokenKind::Number(1.0), "1", 1),
+            token(TokenKind::Plus, "+", 3),
+            token(TokenKind::Number(2.0), "2", 5),
+        ];
+
+        let expr = Parser::from_tokens(tokens).parse().unwrap();
+
+        let Expression::Binary {
+            left,
+            operator,
+            right,
+        } = expr
+        else {
+            panic!("expected a binary expression");
+        };
+        assert!(matches!(*left, Expression::Literal(Literal::Number(n)) if n == 1.0));
+        assert_eq!(operator, TokenKind::Plus);
+        assert!(matches!(*right, Expression::Literal(Literal::Number(n)) if n == 2.0));
+    }
+
+    #[test]
+    fn needs_more_input() {
+        for source in [
+            "if (x) {",
+            "print (1 +",
+            "var s = \"abc",
+            "var l = [1,",
+            "{ {} ",
+        ] {
+            assert!(Parser::needs_more_input(source), "{source}");
+        }
+        for source in [
+            "print 1;",
+            "if (x) { print x; }",
+            "var s = \"a{b\";",
+            "}",
+            "",
+        ] {
+            assert!(!Parser::needs_more_input(source), "{source}");
+        }
+    }
+
+    #[test]
+    fn parse_all_caps_errors() {
+        let source = "print ;".repeat(30);
+
+        let errors = Parser::new(&source).parse_all().unwrap_err();
+        assert_eq!(errors.len(), 21);
+        assert!(matches!(errors.last(), Some(ParserError::TooManyErrors)));
+
+        let errors = Parser::new(&source)
+            .with_max_errors(3)
+            .parse_all()
+            .unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(errors[2], ParserError::UnexpectedToken { .. }));
+        assert_eq!(errors[3].to_string(), "Too many errors; stopping.");
+
+        let errors = Parser::new("print ; print 1; print ;")
+            .parse_all()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn render_with_source_points_at_token() {
+        let source = "var a = 1;\n\tprint a + ;\n";
+        let err = Parser::new(source).parse_statements().unwrap_err();
+
+        assert_eq!(
+            err.render_with_source(source),
+            "[line 2] Error at ';': Expect expression.\n\tprint a + ;\n\t          ^"
+        );
+
+        let source = "1 = 2";
+        let err = Parser::new(source).parse().unwrap_err();
+        let rendered = err.render_with_source(source);
+        let caret = rendered.lines().last().unwrap();
+        assert_eq!(caret.len(), 3);
+        assert!(caret.ends_with('^'));
+    }
+
+    #[test]
+    fn render_with_source_counts_two_character_operators() {
+        let source = "var a = 1 == 2 + ;";
+        let err = Parser::new(source).parse_statements().unwrap_err();
+        let rendered = err.render_with_source(source);
+        let caret = rendered.lines().last().unwrap();
+        assert_eq!(caret.len(), 18);
+        assert_eq!(source.chars().nth(caret.len() - 1), Some(';'));
+    }
+}
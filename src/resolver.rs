@@ -1,5 +1,5 @@
-use crate::{Expression, Interpreter, Statement};
-use std::collections::HashMap;
+use crate::{Expression, Statement};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub enum ResolverError {
@@ -14,6 +14,8 @@ impl std::fmt::Display for ResolverError {
     }
 }
 
+impl std::error::Error for ResolverError {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum FunctionType {
     None,
@@ -29,56 +31,136 @@ enum ClassType {
     Subclass,
 }
 
+/// What resolution learned about a program, beyond the distances it writes
+/// into the AST.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub warnings: Vec<String>,
+}
+
 pub struct Resolver {
     scopes: Vec<HashMap<String, bool>>,
-    pub interpreter: Interpreter,
+    // Local `var`s per scope that have not been read yet.
+    unused: Vec<Vec<String>>,
+    warnings: Vec<String>,
     current_function: FunctionType,
     current_class: ClassType,
+    loops: Vec<Option<String>>,
+    // Globals the program declares further down. Straight-line code can't
+    // use them yet; function bodies can, since they run later.
+    pending_globals: HashSet<String>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Resolver {
-    pub fn new(interpreter: Interpreter) -> Self {
-        let scopes = vec![HashMap::new()];
+    pub fn new() -> Self {
         Resolver {
-            scopes,
-            interpreter,
+            scopes: vec![HashMap::new()],
+            unused: vec![Vec::new()],
+            warnings: Vec::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            loops: Vec::new(),
+            pending_globals: HashSet::new(),
         }
     }
 
+    pub fn analyze(statements: &mut [Statement]) -> Result<Analysis, ResolverError> {
+        let mut resolver = Resolver::new();
+        resolver.resolve_stmts(statements)?;
+        Ok(Analysis {
+            warnings: resolver.warnings,
+        })
+    }
+
     pub fn resolve_stmts(&mut self, statements: &mut [Statement]) -> Result<(), ResolverError> {
+        if self.scopes.len() == 1 {
+            self.pending_globals.extend(statements.iter().filter_map(
+                |statement| match statement {
+                    Statement::Var { name, .. }
+                    | Statement::Function { name, .. }
+                    | Statement::Class { name, .. } => Some(name.clone()),
+                    _ => None,
+                },
+            ));
+        }
         for statement in statements.iter_mut() {
             self.resolve_stmt(statement)?;
         }
         Ok(())
     }
 
+    pub fn resolve_single_expr(&mut self, expr: &mut Expression) -> Result<(), ResolverError> {
+        self.resolve_expr(expr)
+    }
+
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.unused.push(Vec::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        for name in self.unused.pop().unwrap_or_default() {
+            self.warnings
+                .push(format!("Local variable '{name}' is never used."));
+        }
+    }
+
+    fn mark_used(&mut self, name: &str, distance: Option<usize>) {
+        if let Some(dist) = distance {
+            let index = self.unused.len() - 1 - dist;
+            self.unused[index].retain(|unused| unused != name);
+        }
     }
 
     fn resolve_stmt(&mut self, stmt: &mut Statement) -> Result<(), ResolverError> {
         match stmt {
-            Statement::Block(list) => {
-                self.begin_scope();
-                for statement in list.iter_mut() {
+            // Imported files are resolved on their own when they run.
+            Statement::Empty | Statement::Import { .. } => {}
+            Statement::Block {
+                statements, scoped, ..
+            } => {
+                *scoped = statements.iter().any(|statement| {
+                    matches!(
+                        statement,
+                        Statement::Var { .. }
+                            | Statement::Function { .. }
+                            | Statement::Class { .. }
+                    )
+                });
+                if *scoped {
+                    self.begin_scope();
+                }
+                for statement in statements.iter_mut() {
                     self.resolve_stmt(statement)?;
                 }
-                self.end_scope();
+                if *scoped {
+                    self.end_scope();
+                }
             }
-            Statement::Var { name, initializer } => {
+            Statement::Var {
+                name, initializer, ..
+            } => {
                 self.declare(name.as_str())?;
                 if let Some(expr) = initializer {
                     self.resolve_expr(expr)?;
                 }
                 self.define(name.as_str());
+                if self.scopes.len() > 1 {
+                    if let Some(unused) = self.unused.last_mut() {
+                        unused.push(name.clone());
+                    }
+                }
             }
-            Statement::Function { name, params, body } => {
+            Statement::Function {
+                name, params, body, ..
+            } => {
                 self.declare(name.as_str())?;
                 self.define(name.as_str());
                 self.resolve_function(
@@ -87,21 +169,44 @@ impl Resolver {
                     FunctionType::Function,
                 )?;
             }
-            Statement::Expr(expr) | Statement::Print(expr) => {
-                self.resolve_expr(expr)?;
+            Statement::Expr { expression, .. } => {
+                self.resolve_expr(expression)?;
+            }
+            Statement::Print { expressions, .. } => {
+                for expr in expressions.iter_mut() {
+                    self.resolve_expr(expr)?;
+                }
             }
             Statement::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 self.resolve_expr(condition)?;
                 self.resolve_stmt(then_branch)?;
-                if let Some(else_stmt) = else_branch {
-                    self.resolve_stmt(else_stmt)?;
+                // Walk `else if` chains in a loop so they don't grow the stack.
+                let mut next = else_branch.as_deref_mut();
+                while let Some(statement) = next {
+                    match statement {
+                        Statement::If {
+                            condition,
+                            then_branch,
+                            else_branch,
+                            ..
+                        } => {
+                            self.resolve_expr(condition)?;
+                            self.resolve_stmt(then_branch)?;
+                            next = else_branch.as_deref_mut();
+                        }
+                        other => {
+                            self.resolve_stmt(other)?;
+                            next = None;
+                        }
+                    }
                 }
             }
-            Statement::Return { value } => {
+            Statement::Return { value, .. } => {
                 if self.current_function == FunctionType::None {
                     return Err(ResolverError::Message(
                         "Can't return from top-level code.".to_string(),
@@ -117,15 +222,33 @@ impl Resolver {
                 }
                 return Ok(());
             }
-            Statement::While { condition, body } => {
+            Statement::While {
+                condition,
+                body,
+                label,
+                ..
+            } => {
+                self.resolve_expr(condition)?;
+                self.resolve_loop_body(label, body)?;
+            }
+            Statement::DoWhile {
+                body,
+                condition,
+                label,
+                ..
+            } => {
+                self.resolve_loop_body(label, body)?;
                 self.resolve_expr(condition)?;
-                self.resolve_stmt(body)?;
             }
+            Statement::Break { label, .. } => self.resolve_jump("break", label)?,
+            Statement::Continue { label, .. } => self.resolve_jump("continue", label)?,
             Statement::For {
                 initialize,
                 condition,
                 increment,
                 body,
+                label,
+                ..
             } => {
                 self.begin_scope();
 
@@ -135,33 +258,99 @@ impl Resolver {
                 if let Some(con) = condition {
                     self.resolve_expr(con)?;
                 }
-                self.resolve_stmt(body)?;
+                self.resolve_loop_body(label, body)?;
                 if let Some(inc) = increment {
                     self.resolve_expr(inc)?;
                 }
 
                 self.end_scope();
             }
+            Statement::ForIn {
+                name,
+                iterable,
+                body,
+                label,
+                ..
+            } => {
+                self.resolve_expr(iterable)?;
+                self.begin_scope();
+                self.declare(name.as_str())?;
+                self.define(name.as_str());
+                self.resolve_loop_body(label, body)?;
+                self.end_scope();
+            }
+            Statement::Switch {
+                discriminant,
+                cases,
+                default,
+                ..
+            } => {
+                self.resolve_expr(discriminant)?;
+                for (value, body) in cases.iter_mut() {
+                    self.resolve_expr(value)?;
+                    self.begin_scope();
+                    self.resolve_stmts(body)?;
+                    self.end_scope();
+                }
+                if let Some(body) = default {
+                    self.begin_scope();
+                    self.resolve_stmts(body)?;
+                    self.end_scope();
+                }
+            }
             Statement::Class {
                 name,
                 superclass,
                 methods,
-            } => {
-                self.resolve_class(name.as_str(), superclass.as_deref(), methods.as_mut_slice())?
-            }
+                statics,
+                ..
+            } => self.resolve_class(
+                name.as_str(),
+                superclass.as_deref(),
+                methods.as_mut_slice(),
+                statics.as_mut_slice(),
+            )?,
         }
         Ok(())
     }
 
+    fn resolve_loop_body(
+        &mut self,
+        label: &Option<String>,
+        body: &mut Statement,
+    ) -> Result<(), ResolverError> {
+        self.loops.push(label.clone());
+        let result = self.resolve_stmt(body);
+        self.loops.pop();
+        result
+    }
+
+    fn resolve_jump(&self, keyword: &str, label: &Option<String>) -> Result<(), ResolverError> {
+        match label {
+            _ if self.loops.is_empty() => Err(ResolverError::Message(format!(
+                "Can't use '{keyword}' outside of a loop."
+            ))),
+            Some(name) if !self.loops.iter().any(|l| l.as_ref() == Some(name)) => Err(
+                ResolverError::Message(format!("No enclosing loop labeled '{name}'.")),
+            ),
+            _ => Ok(()),
+        }
+    }
+
     fn resolve_class(
         &mut self,
         name: &str,
         superclass: Option<&str>,
         methods: &mut [Statement],
+        statics: &mut [(String, Expression)],
     ) -> Result<(), ResolverError> {
         self.declare(name)?;
         self.define(name);
 
+        for (_, value) in statics.iter_mut() {
+            self.resolve_expr(value)?;
+        }
+
         let enclosing_class = self.current_class;
         let is_subclass = superclass.is_some();
 
@@ -191,6 +380,7 @@ impl Resolver {
                 name: ref method_name,
                 params,
                 body,
+                ..
             } = method
             {
                 let function_type = if method_name == "init" {
@@ -200,7 +390,9 @@ impl Resolver {
                 };
                 self.resolve_function(params.as_slice(), body.as_mut_slice(), function_type)?;
             } else {
-                unreachable!();
+                return Err(ResolverError::Message(format!(
+                    "Class '{name}' has a member that is not a method."
+                )));
             }
         }
 
@@ -234,6 +426,18 @@ impl Resolver {
         Ok(())
     }
 
+    fn check_declared(&self, name: &str, distance: Option<usize>) -> Result<(), ResolverError> {
+        if distance.is_none()
+            && self.current_function == FunctionType::None
+            && self.pending_globals.contains(name)
+        {
+            return Err(ResolverError::Message(format!(
+                "Can't use global '{name}' before its declaration."
+            )));
+        }
+        Ok(())
+    }
+
     fn resolve_local(&self, name: &str) -> Option<usize> {
         self.scopes
             .iter()
@@ -244,6 +448,16 @@ impl Resolver {
     fn resolve_expr(&mut self, expr: &mut Expression) -> Result<(), ResolverError> {
         match expr {
             Expression::Literal(_) | Expression::Group(_) => {}
+            Expression::List(elements) => {
+                for element in elements.iter_mut() {
+                    self.resolve_expr(element)?;
+                }
+            }
+            Expression::Map(entries) => {
+                for (_, value) in entries.iter_mut() {
+                    self.resolve_expr(value)?;
+                }
+            }
             Expression::Unary { expression, .. } => {
                 self.resolve_expr(expression.as_mut())?;
             }
@@ -265,7 +479,9 @@ impl Resolver {
                         }
                     }
                 }
+                self.check_declared(name, distance)?;
                 *resolved = distance;
+                self.mark_used(name, distance);
             }
             Expression::This { resolved } => {
                 if self.current_class == ClassType::None {
@@ -292,6 +508,11 @@ impl Resolver {
                 let distance = self.resolve_local("super");
                 *resolved = distance;
             }
+            Expression::Postfix { name, resolved, .. } => {
+                *resolved = self.resolve_local(name);
+                self.check_declared(name, *resolved)?;
+                self.mark_used(name, *resolved);
+            }
             Expression::Assign {
                 name,
                 value,
@@ -299,9 +520,10 @@ impl Resolver {
             } => {
                 self.resolve_expr(value.as_mut())?;
                 let distance = self.resolve_local(name);
+                self.check_declared(name, distance)?;
                 *resolved = distance;
             }
-            Expression::Call { callee, args } => {
+            Expression::Call { callee, args, .. } => {
                 self.resolve_expr(callee.as_mut())?;
                 for arg in args.iter_mut() {
                     self.resolve_expr(arg)?;
@@ -319,6 +541,19 @@ impl Resolver {
             Expression::Get { object, name: _ } => {
                 self.resolve_expr(object)?;
             }
+            Expression::Lambda { params, body } => {
+                self.resolve_function(params, body, FunctionType::Function)?;
+            }
+            Expression::Block { statements, value } => {
+                self.begin_scope();
+                self.resolve_stmts(statements)?;
+                self.resolve_expr(value)?;
+                self.end_scope();
+            }
+            Expression::Index { object, index } => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+            }
         }
         Ok(())
     }
@@ -326,6 +561,9 @@ impl Resolver {
     fn declare(&mut self, name: &str) -> Result<(), ResolverError> {
         let len = self.scopes.len();
         let is_global = len == 1;
+        if is_global {
+            self.pending_globals.remove(name);
+        }
         if let Some(scope) = self.scopes.last_mut() {
             if scope.contains_key(name) && !is_global {
                 return Err(ResolverError::Message(format!(
@@ -354,6 +592,7 @@ impl Resolver {
     ) -> Result<(), ResolverError> {
         let enclosing_function = self.current_function;
         self.current_function = function_type;
+        let enclosing_loops = std::mem::take(&mut self.loops);
 
         self.begin_scope();
         if matches!(
@@ -370,7 +609,175 @@ impl Resolver {
         self.resolve_stmts(body)?;
         self.end_scope();
 
+        self.loops = enclosing_loops;
         self.current_function = enclosing_function;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, Parser, Resolver, Statement};
+
+    #[test]
+    fn analyze_reports_unused_locals() {
+        let mut statements = Parser::new(
+            "var global; { var used = 1; var unused = 2; print used; } fun f(a) { var b; }",
+        )
+        .parse_statements()
+        .unwrap();
+
+        let analysis = Resolver::analyze(&mut statements).unwrap();
+
+        assert_eq!(
+            analysis.warnings,
+            [
+                "Local variable 'unused' is never used.",
+                "Local variable 'b' is never used."
+            ]
+        );
+    }
+
+    #[test]
+    fn analyze_writes_distances() {
+        let mut statements = Parser::new("{ var a = 1; { print a; var b; } }")
+            .parse_statements()
+            .unwrap();
+
+        Resolver::analyze(&mut statements).unwrap();
+
+        let Statement::Block {
+            statements: outer, ..
+        } = &statements[0]
+        else {
+            panic!("expected a block");
+        };
+        let Statement::Block {
+            statements: inner, ..
+        } = &outer[1]
+        else {
+            panic!("expected a block");
+        };
+        assert!(matches!(
+            inner[0],
+            Statement::Print { ref expressions, .. } if matches!(expressions[..], [Expression::Variable {
+                resolved: Some(1),
+                ..
+            }])
+        ));
+    }
+
+    #[test]
+    fn only_declaring_blocks_are_scoped() {
+        let mut statements =
+            Parser::new("{ print 1; } { var a = 1; print a; } { fun f() {} f(); }")
+                .parse_statements()
+                .unwrap();
+
+        Resolver::analyze(&mut statements).unwrap();
+
+        let scoped: Vec<bool> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Block { scoped, .. } => *scoped,
+                other => panic!("expected a block, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(scoped, vec![false, true, true]);
+    }
+
+    #[test]
+    fn globals_used_before_declaration() {
+        let resolve = |source: &str| {
+            let mut statements = Parser::new(source).parse_statements().unwrap();
+            Resolver::analyze(&mut statements).map(|_| ())
+        };
+
+        let err = resolve("print x; var x = 1;").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't use global 'x' before its declaration."
+        );
+        assert!(resolve("{ x = 2; } var x;").is_err());
+        assert!(resolve("print f(); fun f() { return 1; }").is_err());
+
+        assert!(resolve("fun f() { return x; } var x = 1; print f();").is_ok());
+        assert!(resolve("class A { m() { return B(); } } class B {}").is_ok());
+        assert!(resolve("var x = 1; print x; var x = 2;").is_ok());
+        assert!(resolve("print undeclared;").is_ok());
+    }
+
+    #[test]
+    fn malformed_class_members_are_errors() {
+        let mut statements = vec![Statement::Class {
+            name: "Odd".to_string(),
+            superclass: None,
+            methods: vec![Statement::Empty],
+            statics: Vec::new(),
+            line: 1,
+        }];
+
+        let err = Resolver::analyze(&mut statements).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Class 'Odd' has a member that is not a method."
+        );
+    }
+
+    #[test]
+    fn super_requires_a_superclass() {
+        let resolve = |source: &str| {
+            let mut statements = Parser::new(source).parse_statements().unwrap();
+            Resolver::analyze(&mut statements).map(|_| ())
+        };
+
+        assert!(resolve("class A { f() {} } class B < A { f() { return super.f(); } }").is_ok());
+        for source in [
+            "class A { f() { return super.f(); } }",
+            // A nested root class doesn't inherit the enclosing class's superclass.
+            "class A {} class B < A { f() { class C { g() { super.g(); } } } }",
+        ] {
+            assert_eq!(
+                resolve(source).unwrap_err().to_string(),
+                "Can't use 'super' in a class with no superclass.",
+                "{source}"
+            );
+        }
+        assert_eq!(
+            resolve("fun f() { super.f(); }").unwrap_err().to_string(),
+            "Can't use 'super' outside of a class."
+        );
+    }
+
+    #[test]
+    fn nested_classes_restore_the_enclosing_class_type() {
+        let mut statements = Parser::new(
+            "class A { f() {} } \
+             class B < A { f() { class Inner { g() { return this; } } return super.f(); } }",
+        )
+        .parse_statements()
+        .unwrap();
+        assert!(Resolver::analyze(&mut statements).is_ok());
+
+        let mut statements =
+            Parser::new("class A {} class B < A {} class C { f() { return super.f(); } }")
+                .parse_statements()
+                .unwrap();
+        assert_eq!(
+            Resolver::analyze(&mut statements).unwrap_err().to_string(),
+            "Can't use 'super' in a class with no superclass."
+        );
+    }
+
+    #[test]
+    fn long_else_if_chains() {
+        let mut source = String::from("var x = 999; if (x == 0) print 0;");
+        for i in 1..=1000 {
+            source.push_str(&format!(" else if (x == {i}) {{ var y = {i}; print y; }}"));
+        }
+        source.push_str(" else print x;");
+
+        let mut statements = Parser::new(&source).parse_statements().unwrap();
+        assert!(Resolver::analyze(&mut statements).is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{iter::Peekable, str::Chars};
+use std::{borrow::Cow, iter::Peekable, str::Chars};
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenKind,
@@ -13,11 +13,18 @@ pub enum TokenKind {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Star,
     Dot,
     Comma,
+    Colon,
+    QuestionQuestion,
     Plus,
+    PlusEqual,
+    PlusPlus,
     Minus,
+    MinusMinus,
     Semi,
     Slash,
     Equal,
@@ -32,12 +39,19 @@ pub enum TokenKind {
     Number(f64),
     Identifier,
     And,
+    Break,
+    Case,
     Class,
+    Continue,
+    Default,
+    Do,
     Else,
     False,
     For,
     Fun,
     If,
+    Import,
+    In,
     Nil,
     Or,
     Print,
@@ -45,7 +59,9 @@ pub enum TokenKind {
     Super,
     This,
     True,
+    Unless,
     Var,
+    When,
     While,
     Illegal(IlligalType),
 }
@@ -54,31 +70,82 @@ pub enum TokenKind {
 pub enum IlligalType {
     Unexpected,
     UnterminatedString,
+    InvalidCharLiteral,
+    LiteralTooLong,
+}
+
+/// Default cap, in bytes, on identifiers and string literals.
+pub const DEFAULT_MAX_LITERAL_LEN: usize = 1024 * 1024;
+
+impl TokenKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::LeftParen => "LEFT_PAREN",
+            TokenKind::RightParen => "RIGHT_PAREN",
+            TokenKind::LeftBrace => "LEFT_BRACE",
+            TokenKind::RightBrace => "RIGHT_BRACE",
+            TokenKind::LeftBracket => "LEFT_BRACKET",
+            TokenKind::RightBracket => "RIGHT_BRACKET",
+            TokenKind::Star => "STAR",
+            TokenKind::Dot => "DOT",
+            TokenKind::Comma => "COMMA",
+            TokenKind::Colon => "COLON",
+            TokenKind::QuestionQuestion => "QUESTION_QUESTION",
+            TokenKind::Plus => "PLUS",
+            TokenKind::PlusEqual => "PLUS_EQUAL",
+            TokenKind::PlusPlus => "PLUS_PLUS",
+            TokenKind::Minus => "MINUS",
+            TokenKind::MinusMinus => "MINUS_MINUS",
+            TokenKind::Semi => "SEMICOLON",
+            TokenKind::Slash => "SLASH",
+            TokenKind::Equal => "EQUAL",
+            TokenKind::EqualEqual => "EQUAL_EQUAL",
+            TokenKind::Bang => "BANG",
+            TokenKind::BangEqual => "BANG_EQUAL",
+            TokenKind::Less => "LESS",
+            TokenKind::LessEqual => "LESS_EQUAL",
+            TokenKind::Greater => "GREATER",
+            TokenKind::GreaterEqual => "GREATER_EQUAL",
+            TokenKind::Identifier => "IDENTIFIER",
+            TokenKind::And => "AND",
+            TokenKind::Break => "BREAK",
+            TokenKind::Case => "CASE",
+            TokenKind::Class => "CLASS",
+            TokenKind::Continue => "CONTINUE",
+            TokenKind::Default => "DEFAULT",
+            TokenKind::Do => "DO",
+            TokenKind::Else => "ELSE",
+            TokenKind::For => "FOR",
+            TokenKind::Fun => "FUN",
+            TokenKind::If => "IF",
+            TokenKind::Import => "IMPORT",
+            TokenKind::In => "IN",
+            TokenKind::False => "FALSE",
+            TokenKind::Nil => "NIL",
+            TokenKind::Or => "OR",
+            TokenKind::Print => "PRINT",
+            TokenKind::Return => "RETURN",
+            TokenKind::This => "THIS",
+            TokenKind::True => "TRUE",
+            TokenKind::Unless => "UNLESS",
+            TokenKind::Var => "VAR",
+            TokenKind::When => "WHEN",
+            TokenKind::While => "WHILE",
+            TokenKind::Super => "SUPER",
+            TokenKind::String => "STRING",
+            TokenKind::Number(_) => "NUMBER",
+            TokenKind::Illegal(_) => "ERROR",
+        }
+    }
 }
 
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.kind {
-            TokenKind::LeftParen => write!(f, "LEFT_PAREN ( null"),
-            TokenKind::RightParen => write!(f, "RIGHT_PAREN ) null"),
-            TokenKind::LeftBrace => write!(f, "LEFT_BRACE {{ null"),
-            TokenKind::RightBrace => write!(f, "RIGHT_BRACE }} null"),
-            TokenKind::Star => write!(f, "STAR * null"),
-            TokenKind::Dot => write!(f, "DOT . null"),
-            TokenKind::Comma => write!(f, "COMMA , null"),
-            TokenKind::Plus => write!(f, "PLUS + null"),
-            TokenKind::Minus => write!(f, "MINUS - null"),
-            TokenKind::Semi => write!(f, "SEMICOLON ; null"),
-            TokenKind::Slash => write!(f, "SLASH / null"),
-            TokenKind::Equal => write!(f, "EQUAL = null"),
-            TokenKind::EqualEqual => write!(f, "EQUAL_EQUAL == null"),
-            TokenKind::Bang => write!(f, "BANG ! null"),
-            TokenKind::BangEqual => write!(f, "BANG_EQUAL != null"),
-            TokenKind::Less => write!(f, "LESS < null"),
-            TokenKind::LessEqual => write!(f, "LESS_EQUAL <= null"),
-            TokenKind::Greater => write!(f, "GREATER > null"),
-            TokenKind::GreaterEqual => write!(f, "GREATER_EQUAL >= null"),
-            TokenKind::String => write!(f, "STRING \"{}\" {}", self.literal, self.literal),
+            TokenKind::String => {
+                let literal = show_nul(&self.literal);
+                write!(f, "STRING \"{literal}\" {literal}")
+            }
             TokenKind::Number(num) => {
                 if num.fract() == 0.0 {
                     write!(f, "NUMBER {} {:.1}", self.literal, num)
@@ -86,39 +153,106 @@ impl std::fmt::Display for Token {
                     write!(f, "NUMBER {} {num}", self.literal)
                 }
             }
-            TokenKind::Identifier => write!(f, "IDENTIFIER {} null", self.literal),
-            TokenKind::And => write!(f, "AND {} null", self.literal),
-            TokenKind::Class => write!(f, "CLASS {} null", self.literal),
-            TokenKind::Else => write!(f, "ELSE {} null", self.literal),
-            TokenKind::For => write!(f, "FOR {} null", self.literal),
-            TokenKind::Fun => write!(f, "FUN {} null", self.literal),
-            TokenKind::If => write!(f, "IF {} null", self.literal),
-            TokenKind::False => write!(f, "FALSE {} null", self.literal),
-            TokenKind::Nil => write!(f, "NIL {} null", self.literal),
-            TokenKind::Or => write!(f, "OR {} null", self.literal),
-            TokenKind::Print => write!(f, "PRINT {} null", self.literal),
-            TokenKind::Return => write!(f, "RETURN {} null", self.literal),
-            TokenKind::This => write!(f, "THIS {} null", self.literal),
-            TokenKind::True => write!(f, "TRUE {} null", self.literal),
-            TokenKind::Var => write!(f, "VAR {} null", self.literal),
-            TokenKind::While => write!(f, "WHILE {} null", self.literal),
-            TokenKind::Super => write!(f, "SUPER {} null", self.literal),
             TokenKind::Illegal(ty) => {
                 let word = match ty {
                     IlligalType::UnterminatedString => "Unterminated string .".to_string(),
                     IlligalType::Unexpected => format!("Unexpected character: {}", self.literal),
+                    IlligalType::InvalidCharLiteral => {
+                        "Character literal must contain exactly one character.".to_string()
+                    }
+                    IlligalType::LiteralTooLong => "Literal is too long.".to_string(),
                 };
 
                 write!(f, "[line {}] Error: {}", self.line, word)
             }
+            kind => write!(f, "{} {} null", kind.name(), self.literal),
+        }
+    }
+}
+
+impl Token {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"kind\":\"{}\",\"literal\":\"{}\",\"line\":{},\"column\":{}}}",
+            self.kind.name(),
+            escape_json(&self.literal),
+            self.line,
+            self.column
+        )
+    }
+}
+
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    let entries: Vec<String> = tokens.iter().map(Token::to_json).collect();
+    format!("[{}]", entries.join(","))
+}
+
+/// Spells NUL characters as `\0`, so strings holding them stay readable.
+pub(crate) fn show_nul(s: &str) -> Cow<'_, str> {
+    if s.contains('\0') {
+        Cow::Owned(s.replace('\0', "\\0"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: IlligalType,
+    pub literal: String,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            IlligalType::UnterminatedString => {
+                write!(f, "[line {}] Error: Unterminated string.", self.line)
+            }
+            IlligalType::Unexpected => write!(
+                f,
+                "[line {}] Error: Unexpected character: {}",
+                self.line, self.literal
+            ),
+            IlligalType::InvalidCharLiteral => write!(
+                f,
+                "[line {}] Error: Character literal must contain exactly one character.",
+                self.line
+            ),
+            IlligalType::LiteralTooLong => {
+                write!(f, "[line {}] Error: Literal is too long.", self.line)
+            }
         }
     }
 }
 
+impl std::error::Error for LexError {}
+
+#[derive(Clone)]
 pub struct Lexer<'c> {
     input: Peekable<Chars<'c>>,
     line: usize,
     column: usize,
+    position: usize,
+    total_len: Option<usize>,
+    max_literal_len: usize,
 }
 
 impl<'c> Lexer<'c> {
@@ -127,20 +261,73 @@ impl<'c> Lexer<'c> {
             input: input.chars().peekable(),
             line: 1,
             column: 1,
+            position: 0,
+            total_len: None,
+            max_literal_len: DEFAULT_MAX_LITERAL_LEN,
         }
     }
 
+    /// Caps identifiers and string literals at `len` bytes; longer ones are
+    /// scanned past and reported as `LiteralTooLong`.
+    pub fn with_max_literal_len(mut self, len: usize) -> Self {
+        self.max_literal_len = len;
+        self
+    }
+
+    /// Like `new`, but remembers the input length so `total_len` can be used
+    /// alongside `byte_position` for progress reporting.
+    pub fn from_str_with_len(input: &'c str) -> Self {
+        Lexer {
+            total_len: Some(input.len()),
+            ..Lexer::new(input)
+        }
+    }
+
+    /// Bytes of input consumed so far.
+    pub fn byte_position(&self) -> usize {
+        self.position
+    }
+
+    pub fn total_len(&self) -> Option<usize> {
+        self.total_len
+    }
+
+    /// Like iterating the lexer directly, but turns `Illegal` tokens into errors.
+    pub fn tokens_checked(self) -> impl Iterator<Item = Result<Token, LexError>> + 'c {
+        self.map(|token| match token.kind {
+            TokenKind::Illegal(kind) => Err(LexError {
+                line: token.line,
+                column: token.column,
+                kind,
+                literal: token.literal,
+            }),
+            _ => Ok(token),
+        })
+    }
+
     fn advance(&mut self) -> Option<char> {
         let ch = self.input.next()?;
-        if ch == '\n' {
-            self.line += 1;
-            self.column = 1;
-        } else {
-            self.column += 1;
+        self.position += ch.len_utf8();
+        match ch {
+            // The '\n' of a "\r\n" pair does the line break.
+            '\r' if self.input.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                self.line += 1;
+                self.column = 1;
+            }
+            _ => self.column += 1,
         }
         Some(ch)
     }
 
+    /// Consumes `expected` if it is next.
+    fn advance_if(&mut self, expected: char) -> Option<char> {
+        let ch = self.input.next_if_eq(&expected)?;
+        self.position += ch.len_utf8();
+        self.column += 1;
+        Some(ch)
+    }
+
     fn next_token(&mut self) -> Option<Token> {
         self.skip_whitespace();
 
@@ -156,14 +343,32 @@ impl<'c> Lexer<'c> {
             ')' => TokenKind::RightParen,
             '{' => TokenKind::LeftBrace,
             '}' => TokenKind::RightBrace,
+            '[' => TokenKind::LeftBracket,
+            ']' => TokenKind::RightBracket,
             '*' => TokenKind::Star,
             '.' => TokenKind::Dot,
             ',' => TokenKind::Comma,
-            '+' => TokenKind::Plus,
+            ':' => TokenKind::Colon,
+            '?' => {
+                if let Some(next_ch) = self.advance_if('?') {
+                    literal.push(next_ch);
+                    TokenKind::QuestionQuestion
+                } else {
+                    TokenKind::Illegal(IlligalType::Unexpected)
+                }
+            }
+            '+' => {
+                if let Some(next_ch) = self.advance_if('=') {
+                    literal.push(next_ch);
+                    TokenKind::PlusEqual
+                } else {
+                    TokenKind::Plus
+                }
+            }
             '-' => TokenKind::Minus,
             ';' => TokenKind::Semi,
             '/' => {
-                if self.input.next_if_eq(&'/').is_some() {
+                if self.advance_if('/').is_some() {
                     self.next_line();
                     return self.next_token();
                 } else {
@@ -171,7 +376,7 @@ impl<'c> Lexer<'c> {
                 }
             }
             '=' => {
-                if let Some(next_ch) = self.input.next_if_eq(&'=') {
+                if let Some(next_ch) = self.advance_if('=') {
                     literal.push(next_ch);
                     TokenKind::EqualEqual
                 } else {
@@ -179,7 +384,7 @@ impl<'c> Lexer<'c> {
                 }
             }
             '!' => {
-                if let Some(next_ch) = self.input.next_if_eq(&'=') {
+                if let Some(next_ch) = self.advance_if('=') {
                     literal.push(next_ch);
                     TokenKind::BangEqual
                 } else {
@@ -187,7 +392,7 @@ impl<'c> Lexer<'c> {
                 }
             }
             '<' => {
-                if let Some(next_ch) = self.input.next_if_eq(&'=') {
+                if let Some(next_ch) = self.advance_if('=') {
                     literal.push(next_ch);
                     TokenKind::LessEqual
                 } else {
@@ -195,7 +400,7 @@ impl<'c> Lexer<'c> {
                 }
             }
             '>' => {
-                if let Some(next_ch) = self.input.next_if_eq(&'=') {
+                if let Some(next_ch) = self.advance_if('=') {
                     literal.push(next_ch);
                     TokenKind::GreaterEqual
                 } else {
@@ -203,20 +408,17 @@ impl<'c> Lexer<'c> {
                 }
             }
             '"' => {
-                literal = String::new();
-                let mut found_closing_quote = false;
-                while let Some(c) = self.advance() {
-                    if c == '"' {
-                        found_closing_quote = true;
-                        break;
-                    }
-                    literal.push(c);
-                }
-
-                if found_closing_quote {
-                    TokenKind::String
+                let (kind, text) = self.string('"');
+                literal = text;
+                kind
+            }
+            '\'' => {
+                let (kind, text) = self.string('\'');
+                literal = text;
+                if kind == TokenKind::String && literal.chars().count() != 1 {
+                    TokenKind::Illegal(IlligalType::InvalidCharLiteral)
                 } else {
-                    TokenKind::Illegal(IlligalType::UnterminatedString)
+                    kind
                 }
             }
             '0'..='9' => {
@@ -251,30 +453,51 @@ impl<'c> Lexer<'c> {
                         number.push_str(&next_number);
                     }
                 }
-                let num: f64 = number.parse().unwrap();
+                let kind = number_kind(&number);
                 literal = number;
-                TokenKind::Number(num)
+                kind
             }
             'a'..='z' | 'A'..='Z' | '_' => {
+                let mut too_long = false;
                 while let Some(&next) = self.input.peek() {
                     if !next.is_whitespace() || next.is_ascii_digit() || next == '_' {
-                        if next.is_ascii_punctuation() && next != '_' {
+                        // Control characters such as NUL are never part of a name.
+                        if (next.is_ascii_punctuation() && next != '_') || next.is_control() {
                             break;
                         }
-                        literal.push(next);
+                        if literal.len() + next.len_utf8() > self.max_literal_len {
+                            too_long = true;
+                        } else if !too_long {
+                            literal.push(next);
+                        }
                         self.advance();
                     } else {
                         break;
                     }
                 }
+                if too_long {
+                    return Some(Token {
+                        kind: TokenKind::Illegal(IlligalType::LiteralTooLong),
+                        literal,
+                        line: start_line,
+                        column: start_column,
+                    });
+                }
                 match literal.as_str() {
                     "and" => TokenKind::And,
+                    "break" => TokenKind::Break,
+                    "case" => TokenKind::Case,
                     "class" => TokenKind::Class,
+                    "continue" => TokenKind::Continue,
+                    "default" => TokenKind::Default,
+                    "do" => TokenKind::Do,
                     "else" => TokenKind::Else,
                     "false" => TokenKind::False,
                     "for" => TokenKind::For,
                     "fun" => TokenKind::Fun,
                     "if" => TokenKind::If,
+                    "import" => TokenKind::Import,
+                    "in" => TokenKind::In,
                     "nil" => TokenKind::Nil,
                     "or" => TokenKind::Or,
                     "print" => TokenKind::Print,
@@ -282,7 +505,9 @@ impl<'c> Lexer<'c> {
                     "super" => TokenKind::Super,
                     "this" => TokenKind::This,
                     "true" => TokenKind::True,
+                    "unless" => TokenKind::Unless,
                     "var" => TokenKind::Var,
+                    "when" => TokenKind::When,
                     "while" => TokenKind::While,
                     _ => TokenKind::Identifier,
                 }
@@ -298,6 +523,119 @@ impl<'c> Lexer<'c> {
         })
     }
 
+    /// Scans a quoted literal up to the closing `quote`; the opening quote has
+    /// already been consumed.
+    fn string(&mut self, quote: char) -> (TokenKind, String) {
+        let mut literal = String::new();
+        let mut found_closing_quote = false;
+        let mut invalid_escape = None;
+        let mut too_long = false;
+        while let Some(c) = self.advance() {
+            if c == quote {
+                found_closing_quote = true;
+                break;
+            }
+            let ch = if c == '\\' {
+                match self.escape(quote) {
+                    Ok(ch) => ch,
+                    Err(text) => {
+                        invalid_escape.get_or_insert(text);
+                        continue;
+                    }
+                }
+            } else {
+                c
+            };
+            if literal.len() + ch.len_utf8() > self.max_literal_len {
+                too_long = true;
+            } else if !too_long {
+                literal.push(ch);
+            }
+        }
+
+        if !found_closing_quote {
+            (TokenKind::Illegal(IlligalType::UnterminatedString), literal)
+        } else if too_long {
+            (TokenKind::Illegal(IlligalType::LiteralTooLong), literal)
+        } else if let Some(text) = invalid_escape {
+            (TokenKind::Illegal(IlligalType::Unexpected), text)
+        } else {
+            (TokenKind::String, literal)
+        }
+    }
+
+    /// Decodes an escape sequence; the backslash has already been consumed.
+    /// `\0`, `\xHH` and `\u{…}` decode in both kinds of literal, and a
+    /// malformed `\x` or `\u` is an error, so `"C:\users"` does not lex. The
+    /// single-character escapes only apply to `'…'` literals; elsewhere an
+    /// unknown escape keeps its backslash, so `"C:\"` is a complete string.
+    fn escape(&mut self, quote: char) -> Result<char, String> {
+        let escaped = match self.input.peek() {
+            Some('n') if quote == '\'' => '\n',
+            Some('t') if quote == '\'' => '\t',
+            Some('r') if quote == '\'' => '\r',
+            Some('\\') if quote == '\'' => '\\',
+            Some('"') if quote == '\'' => '"',
+            Some('\'') if quote == '\'' => '\'',
+            Some('0') => '\0',
+            Some('u') => return self.unicode_escape(),
+            Some('x') => return self.hex_escape(),
+            _ => return Ok('\\'),
+        };
+        self.advance();
+        Ok(escaped)
+    }
+
+    /// Decodes a `\xHH` escape, which needs exactly two hex digits; the
+    /// backslash has already been consumed.
+    fn hex_escape(&mut self) -> Result<char, String> {
+        let mut text = String::from("\\");
+        text.extend(self.advance());
+
+        let mut value = 0;
+        for _ in 0..2 {
+            match self.input.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    value = value * 16 + digit;
+                    text.extend(self.advance());
+                }
+                None => return Err(text),
+            }
+        }
+        char::from_u32(value).ok_or(text)
+    }
+
+    /// Decodes a `\u{XXXX}` escape; the backslash has already been consumed.
+    /// On failure, returns the malformed escape text scanned so far.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let mut text = String::from("\\");
+        text.extend(self.advance());
+
+        if self.input.peek() != Some(&'{') {
+            return Err(text);
+        }
+        text.extend(self.advance());
+
+        let mut digits = String::new();
+        while let Some(&c) = self.input.peek() {
+            if !c.is_ascii_hexdigit() || digits.len() == 6 {
+                break;
+            }
+            digits.push(c);
+            text.extend(self.advance());
+        }
+
+        if self.input.peek() != Some(&'}') {
+            return Err(text);
+        }
+        text.extend(self.advance());
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(text)
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(&c) = self.input.peek() {
             if c.is_ascii_whitespace() {
@@ -310,7 +648,7 @@ impl<'c> Lexer<'c> {
 
     fn next_line(&mut self) {
         while let Some(&c) = self.input.peek() {
-            if c != '\n' {
+            if c != '\n' && c != '\r' {
                 self.advance();
             } else {
                 break;
@@ -325,9 +663,19 @@ impl Iterator for Lexer<'_> {
         self.next_token()
     }
 }
+/// Converts scanned number text, reporting text that does not parse as an
+/// unexpected token instead of panicking.
+fn number_kind(number: &str) -> TokenKind {
+    match number.parse() {
+        Ok(num) => TokenKind::Number(num),
+        Err(_) => TokenKind::Illegal(IlligalType::Unexpected),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Lexer, Token, TokenKind};
+    use super::number_kind;
+    use crate::{tokens_to_json, IlligalType, LexError, Lexer, Token, TokenKind};
 
     #[test]
     fn empty() {
@@ -530,43 +878,43 @@ mod tests {
                 kind: TokenKind::Equal,
                 literal: "=".to_string(),
                 line: 1,
-                column: 2,
+                column: 3,
             },
             Token {
                 kind: TokenKind::BangEqual,
                 literal: "!=".to_string(),
                 line: 1,
-                column: 3,
+                column: 4,
             },
             Token {
                 kind: TokenKind::Bang,
                 literal: "!".to_string(),
                 line: 1,
-                column: 4,
+                column: 6,
             },
             Token {
                 kind: TokenKind::Less,
                 literal: "<".to_string(),
                 line: 1,
-                column: 5,
+                column: 7,
             },
             Token {
                 kind: TokenKind::LessEqual,
                 literal: "<=".to_string(),
                 line: 1,
-                column: 6,
+                column: 8,
             },
             Token {
                 kind: TokenKind::Greater,
                 literal: ">".to_string(),
                 line: 1,
-                column: 7,
+                column: 10,
             },
             Token {
                 kind: TokenKind::GreaterEqual,
                 literal: ">=".to_string(),
                 line: 1,
-                column: 8,
+                column: 11,
             },
         ];
 
@@ -574,4 +922,253 @@ mod tests {
 
         assert_eq!(actual_tokens, expected_tokens);
     }
+
+    #[test]
+    fn byte_position_tracks_consumed_input() {
+        let source = "var café = 1 >= 2; // done\nprint \"ok\";";
+        let mut lexer = Lexer::from_str_with_len(source);
+        assert_eq!(lexer.total_len(), Some(source.len()));
+
+        let mut last = lexer.byte_position();
+        assert_eq!(last, 0);
+        while lexer.next().is_some() {
+            assert!(lexer.byte_position() > last);
+            last = lexer.byte_position();
+        }
+        assert_eq!(lexer.byte_position(), source.len());
+    }
+
+    #[test]
+    fn literals_over_the_limit_are_illegal() {
+        let tokens: Vec<Token> = Lexer::new("\"abcdef\" \"abc\" abcdef abc")
+            .with_max_literal_len(4)
+            .collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Illegal(IlligalType::LiteralTooLong),
+                TokenKind::String,
+                TokenKind::Illegal(IlligalType::LiteralTooLong),
+                TokenKind::Identifier,
+            ]
+        );
+        assert_eq!(tokens[0].literal, "abcd");
+    }
+
+    #[test]
+    fn scanning_unicode_escapes() {
+        let tokens: Vec<Token> = Lexer::new(r#""caf\u{e9}" "\u{1F600}!""#).collect();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].kind, TokenKind::String);
+        assert_eq!(tokens[0].literal, "café");
+        assert_eq!(tokens[1].kind, TokenKind::String);
+        assert_eq!(tokens[1].literal, "😀!");
+    }
+
+    #[test]
+    fn scanning_hex_escapes() {
+        let tokens: Vec<Token> = Lexer::new(r#""\x48\x69" "\x4a1" "\x4" "\xzz""#).collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::String,
+                TokenKind::String,
+                TokenKind::Illegal(IlligalType::Unexpected),
+                TokenKind::Illegal(IlligalType::Unexpected),
+            ]
+        );
+        assert_eq!(tokens[0].literal, "Hi");
+        assert_eq!(tokens[1].literal, "J1");
+        assert_eq!(tokens[2].literal, "\\x4");
+    }
+
+    #[test]
+    fn scanning_nul_escapes() {
+        let tokens: Vec<Token> = Lexer::new(r#""a\0b""#).collect();
+        assert_eq!(tokens[0].kind, TokenKind::String);
+        assert_eq!(tokens[0].literal, "a\0b");
+        assert_eq!(tokens[0].to_string(), r#"STRING "a\0b" a\0b"#);
+
+        // A raw NUL in the source ends an identifier and is itself an error.
+        let tokens: Vec<Token> = Lexer::new("ab\0c").collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Illegal(IlligalType::Unexpected),
+                TokenKind::Identifier,
+            ]
+        );
+        assert_eq!(tokens[0].literal, "ab");
+    }
+
+    #[test]
+    fn scanning_malformed_unicode_escapes() {
+        for input in [
+            r#""\u{zz}""#,
+            r#""\u41""#,
+            r#""\u{D800}""#,
+            r#""\u{}""#,
+            r#""C:\users""#,
+        ] {
+            let tokens: Vec<Token> = Lexer::new(input).collect();
+
+            assert_eq!(tokens.len(), 1, "{input}");
+            assert_eq!(
+                tokens[0].kind,
+                TokenKind::Illegal(IlligalType::Unexpected),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_number_text_is_illegal() {
+        assert_eq!(number_kind("12.5"), TokenKind::Number(12.5));
+        for text in ["1.2.3", "", "1e"] {
+            assert_eq!(
+                number_kind(text),
+                TokenKind::Illegal(IlligalType::Unexpected),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn checked_tokens_without_errors() {
+        let tokens: Result<Vec<Token>, LexError> =
+            Lexer::new("var a = 1;").tokens_checked().collect();
+
+        assert_eq!(tokens.unwrap().len(), 5);
+    }
+
+    #[test]
+    fn checked_tokens_with_unterminated_string() {
+        let tokens: Result<Vec<Token>, LexError> =
+            Lexer::new("print \"oops;").tokens_checked().collect();
+
+        assert_eq!(
+            tokens,
+            Err(LexError {
+                line: 1,
+                column: 7,
+                kind: IlligalType::UnterminatedString,
+                literal: "oops;".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn carriage_returns_end_lines() {
+        let tokens: Vec<Token> =
+            Lexer::new("var a;\r\nprint a;\rprint // note\r\n  a;\r\n").collect();
+        let positions: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.literal.as_str(), t.line, t.column))
+            .collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                ("var", 1, 1),
+                ("a", 1, 5),
+                (";", 1, 6),
+                ("print", 2, 1),
+                ("a", 2, 7),
+                (";", 2, 8),
+                ("print", 3, 1),
+                ("a", 4, 3),
+                (";", 4, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_string_reports_start_position() {
+        let tokens: Vec<Token> = Lexer::new("var a;\n  \"never\nclosed\n").collect();
+        let error = tokens.last().unwrap();
+
+        assert_eq!(
+            error.kind,
+            TokenKind::Illegal(IlligalType::UnterminatedString)
+        );
+        assert_eq!((error.line, error.column), (2, 3));
+    }
+
+    #[test]
+    fn doubled_plus_and_minus_are_separate_tokens() {
+        let kinds: Vec<TokenKind> = Lexer::new("a--b++").map(|t| t.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::Minus,
+                TokenKind::Minus,
+                TokenKind::Identifier,
+                TokenKind::Plus,
+                TokenKind::Plus,
+            ]
+        );
+    }
+
+    #[test]
+    fn double_quoted_strings_keep_backslashes() {
+        let tokens: Vec<Token> = Lexer::new(r#""a\" "C:\n\t" "\'""#).collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+
+        assert_eq!(kinds, vec![TokenKind::String; 3]);
+        assert_eq!(tokens[0].literal, "a\\");
+        assert_eq!(tokens[1].literal, "C:\\n\\t");
+        assert_eq!(tokens[2].literal, "\\'");
+        assert_eq!(tokens[0].to_string(), r#"STRING "a\" a\"#);
+    }
+
+    #[test]
+    fn scanning_character_literals() {
+        let tokens: Vec<Token> = Lexer::new(r"'x' '\n' 'ab' ''").collect();
+        let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::String,
+                TokenKind::String,
+                TokenKind::Illegal(IlligalType::InvalidCharLiteral),
+                TokenKind::Illegal(IlligalType::InvalidCharLiteral),
+            ]
+        );
+        assert_eq!(tokens[0].literal, "x");
+        assert_eq!(tokens[1].literal, "\n");
+    }
+
+    #[test]
+    fn tokens_as_json() {
+        let tokens: Vec<Token> = Lexer::new("( )").collect();
+
+        assert_eq!(
+            tokens_to_json(&tokens),
+            concat!(
+                r#"[{"kind":"LEFT_PAREN","literal":"(","line":1,"column":1},"#,
+                r#"{"kind":"RIGHT_PAREN","literal":")","line":1,"column":3}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn json_escapes_literals() {
+        let tokens: Vec<Token> = Lexer::new("\"a\\b\n\" '\"'").collect();
+
+        assert_eq!(
+            tokens[0].to_json(),
+            r#"{"kind":"STRING","literal":"a\\b\n","line":1,"column":1}"#
+        );
+        assert!(tokens[1].to_json().contains(r#""literal":"\"""#));
+    }
 }
@@ -17,9 +17,19 @@ pub trait Visitor<T, E: std::error::Error> {
         right: &Expression,
     ) -> Result<T, E>;
 
-    fn visit_while(&mut self, condition: &Expression, body: &Statement) -> Result<(), E>;
+    fn visit_while(
+        &mut self,
+        condition: &Expression,
+        body: &Statement,
+        label: Option<&str>,
+    ) -> Result<(), E>;
 
-    fn visit_call_expr(&mut self, callee: &Expression, args: &[Expression]) -> Result<T, E>;
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        line: usize,
+    ) -> Result<T, E>;
 
     fn visit_function_stms(&mut self, name: &str, params: &[String], body: &[Statement]);
 
@@ -30,6 +40,7 @@ pub trait Visitor<T, E: std::error::Error> {
         name: &str,
         superclass: Option<&str>,
         methods: &[Statement],
+        statics: &[(String, Expression)],
     ) -> Result<(), E>;
 
     fn visit_get_expr(&mut self, expr: &Expression, name: String) -> Result<T, E>;
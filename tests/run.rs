@@ -0,0 +1,1391 @@
+use std::{
+    path::PathBuf,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+struct Output {
+    stdout: String,
+    stderr: String,
+    code: i32,
+}
+
+fn write_source(source: &str) -> PathBuf {
+    let id = NEXT_FILE.fetch_add(1, Ordering::SeqCst);
+    let path = std::env::temp_dir().join(format!("lox-run-{}-{id}.lox", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn lox(command: &str, source: &str) -> Output {
+    let path = write_source(source);
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg(command)
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    Output {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        code: output.status.code().unwrap(),
+    }
+}
+
+fn run(source: &str) -> Output {
+    lox("run", source)
+}
+
+#[test]
+fn type_names() {
+    let output = run(r#"
+        class Foo {}
+        fun bar() {}
+        print type(1);
+        print type(true);
+        print type(nil);
+        print type("s");
+        print type(bar);
+        print type(clock);
+        print type(Foo);
+        print type(Foo());
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "number\nboolean\nnil\nstring\nfunction\nfunction\nclass\ninstance\n"
+    );
+    assert_eq!(output.code, 0);
+}
+
+#[test]
+fn write_without_newline() {
+    let output = run(r#"
+        write("a");
+        write(1);
+        write(nil);
+        print "";
+    "#);
+
+    assert_eq!(output.stdout, "a1nil\n");
+}
+
+#[test]
+fn math_natives() {
+    let output = run(r#"
+        print floor(3.7);
+        print ceil(3.2);
+        print round(2.5);
+        print abs(-4);
+        print sqrt(16);
+        print floordiv(7, 2);
+        print floordiv(-7, 2);
+        print pow(2, 8);
+        print log(1);
+        print log10(1000);
+        print sin(0);
+        print cos(0);
+        print tan(0);
+    "#);
+
+    assert_eq!(output.stdout, "3\n4\n3\n4\n4\n3\n-4\n256\n0\n3\n0\n1\n0\n");
+}
+
+#[test]
+fn math_natives_reject_invalid_operands() {
+    let output = run("print sqrt(-1);");
+    assert_eq!(output.code, 70);
+    assert_eq!(
+        output.stderr,
+        "Can't take the square root of a negative number.\n"
+    );
+
+    let output = run(r#"print floor("3.7");"#);
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Operand must be a number.\n");
+
+    let output = run("print log(0);");
+    assert_eq!(output.code, 70);
+    assert_eq!(
+        output.stderr,
+        "Can't take the logarithm of a non-positive number.\n"
+    );
+
+    let output = run(r#"print pow(2, "8");"#);
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Operand must be a number.\n");
+
+    let output = run("print floordiv(1, 0);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Division by zero.\n");
+}
+
+#[test]
+fn variadic_min_and_max() {
+    let output = run(r#"
+        print min(3, 1);
+        print max(3, 1);
+        print min(5, 2, 8, -1, 4);
+        print max(5, 2, 8, -1, 4);
+    "#);
+
+    assert_eq!(output.stdout, "1\n3\n-1\n8\n");
+}
+
+#[test]
+fn variadic_arity_is_checked() {
+    let output = run("min();");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Expected at least 1 arguments but got 0.\n");
+}
+
+#[test]
+fn postfix_increment_and_decrement() {
+    let output = run(r#"
+        var i = 0;
+        print i++;
+        print i;
+        print i--;
+        print i;
+        for (var j = 0; j < 3; j++) write(j);
+        print "";
+        print --5;
+    "#);
+
+    assert_eq!(output.stdout, "0\n1\n1\n0\n012\n5\n");
+}
+
+#[test]
+fn double_minus_between_operands_subtracts() {
+    let output = run(r#"
+        var a = 5;
+        var b = 2;
+        print 1--1;
+        print a--b;
+        print a - -b;
+        print a;
+        // A '-' can start an operand, so this stays `a - (-(-1))`.
+        print a-- - 1;
+        print a--;
+        print a;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "2\n7\n7\n5\n4\n5\n4\n");
+}
+
+#[test]
+fn postfix_operators_before_binary_operators() {
+    let output = run(r#"
+        var i = 5;
+        print i++ - 1;
+        print i;
+        print i-- + 1;
+        print i;
+        print i++ * 2;
+        print i;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "4\n6\n7\n5\n10\n6\n");
+}
+
+#[test]
+fn for_in_over_list() {
+    let output = run(r#"
+        var total = 0;
+        for (x in [1, 2, 3]) {
+            print x;
+            total = total + x;
+        }
+        print total;
+        for (key in {only: "value"}) print key;
+    "#);
+
+    assert_eq!(output.stdout, "1\n2\n3\n6\nonly\n");
+}
+
+#[test]
+fn for_in_rejects_non_iterables() {
+    let output = run("for (x in 42) print x;");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Can only iterate over lists and maps.\n");
+}
+
+#[test]
+fn clock_ms_and_sleep() {
+    let output = run(r#"
+        var start = clock_ms();
+        print start > 0;
+        print start == floor(start);
+        print sleep(0);
+    "#);
+
+    assert_eq!(output.stdout, "true\ntrue\nnil\n");
+}
+
+#[test]
+fn sleep_rejects_bad_durations() {
+    for duration in ["1/0", "-1/0", "0/0", "-1"] {
+        let output = run(&format!("sleep({duration});"));
+        assert_eq!(output.code, 70, "{duration}");
+        assert_eq!(
+            output.stderr, "Sleep duration must be a finite, non-negative number.\n",
+            "{duration}"
+        );
+    }
+}
+
+#[test]
+fn passing_assertions() {
+    let output = run(r#"
+        print assert(1 < 2, "math works");
+        assert(true);
+    "#);
+
+    assert_eq!(output.stdout, "nil\n");
+    assert_eq!(output.code, 0);
+}
+
+#[test]
+fn failing_assertions() {
+    let output = run(r#"assert(1 > 2, "math is broken"); print "unreachable";"#);
+    assert_eq!(output.stdout, "");
+    assert_eq!(output.stderr, "math is broken\n");
+    assert_eq!(output.code, 70);
+
+    let output = run("assert(nil);");
+    assert_eq!(output.stderr, "Assertion failed.\n");
+    assert_eq!(output.code, 70);
+}
+
+#[test]
+fn instances_print_with_to_string() {
+    let output = run(r#"
+        class Point {
+            init(x, y) {
+                this.x = x;
+                this.y = y;
+            }
+            toString() {
+                return "(" + type(this.x) + ", " + type(this.y) + ")";
+            }
+        }
+        class Plain {}
+        print Point(1, 2);
+        print Plain();
+        write(Point(1, 2));
+        write(Plain());
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "(number, number)\nPlain instance\n(number, number)Plain instance"
+    );
+}
+
+#[test]
+fn tokenize_as_json() {
+    let output = lox("tokenize", "var x;");
+    assert_eq!(
+        output.stdout,
+        "VAR var null\nIDENTIFIER x null\nSEMICOLON ; null\nEOF  null\n"
+    );
+
+    let path = write_source("( )");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["tokenize", "--json"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"kind":"LEFT_PAREN","literal":"(","line":1,"column":1},"#,
+            r#"{"kind":"RIGHT_PAREN","literal":")","line":1,"column":3}]"#,
+            "\n"
+        )
+    );
+
+    let path = write_source("a >= b");
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["tokenize", "--json"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(r#"{"kind":"IDENTIFIER","literal":"b","line":1,"column":6}"#));
+}
+
+#[test]
+fn list_indexing() {
+    let output = run(r#"
+        var list = [10, 20, 30];
+        print list[0];
+        print list[2];
+        print [10, 20, 30][-1];
+        print list[-3];
+        print {a: 1}["a"];
+    "#);
+
+    assert_eq!(output.stdout, "10\n30\n30\n10\n1\n");
+}
+
+#[test]
+fn list_index_out_of_range() {
+    let output = run("print [10, 20, 30][-4];");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Index out of range.\n");
+
+    let output = run("print [10, 20, 30][3];");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Index out of range.\n");
+}
+
+#[test]
+fn map_keys_and_values() {
+    let output = run(r#"
+        var map = {a: 1, b: 2, c: 3};
+        var count = 0;
+        for (key in keys(map)) count = count + 1;
+        print count;
+        var total = 0;
+        for (value in values(map)) total = total + value;
+        print total;
+        print keys({only: 1});
+        print values({});
+    "#);
+
+    assert_eq!(output.stdout, "3\n6\n[only]\n[]\n");
+
+    let output = run("keys([1]);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Argument must be a map.\n");
+}
+
+#[test]
+fn undefined_variables_are_reported_by_name() {
+    let output = run("var x = nil; print x; print y;");
+    assert_eq!(output.stdout, "nil\n");
+    assert_eq!(output.stderr, "Undefined variable 'y'.\n");
+    assert_eq!(output.code, 70);
+
+    let output = run("y = 1;");
+    assert_eq!(output.stderr, "Undefined variable 'y'.\n");
+    assert_eq!(output.code, 70);
+}
+
+#[test]
+fn when_runs_the_first_matching_case() {
+    let output = run(r#"
+        fun describe(n) {
+            when (n) {
+                case 1:
+                    return "one";
+                case 1 + 1:
+                    var word = "two";
+                    return word;
+                default:
+                    return "many";
+            }
+        }
+        print describe(1);
+        print describe(2);
+        print describe(3);
+        when ("x") {
+            case "x": print "first";
+            case "x": print "second";
+        }
+        when (nil) { case false: print "unreachable"; }
+    "#);
+
+    assert_eq!(output.stdout, "one\ntwo\nmany\nfirst\n");
+    assert_eq!(output.code, 0);
+}
+
+#[test]
+fn for_loop_closures_capture_each_iteration() {
+    let output = run(r#"
+        var first;
+        var second;
+        var third;
+        for (var i = 0; i < 3; i++) {
+            fun get() { return i; }
+            if (i == 0) first = get;
+            if (i == 1) second = get;
+            if (i == 2) third = get;
+        }
+        print first();
+        print second();
+        print third();
+    "#);
+
+    assert_eq!(output.stdout, "0\n1\n2\n");
+}
+
+#[test]
+fn cloned_instances_are_independent() {
+    let output = run(r#"
+        class Point {
+            init(x) { this.x = x; }
+            describe() { return this.x; }
+        }
+        var original = Point(1);
+        var copy = clone(original);
+        copy.x = 2;
+        print original.x;
+        print copy.describe();
+        print copy;
+    "#);
+
+    assert_eq!(output.stdout, "1\n2\nPoint instance\n");
+
+    let output = run("clone(1);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Can only clone instances.\n");
+}
+
+#[test]
+fn class_constants() {
+    let output = run(r#"
+        class Math {
+            var PI = 3.14159;
+            var TAU = 2 * 3.14159;
+            square(x) { return x * x; }
+        }
+        class Trig < Math {}
+        print Math.PI;
+        print Math.TAU;
+        print Trig.PI;
+        print Math().square(3);
+    "#);
+
+    assert_eq!(output.stdout, "3.14159\n6.28318\n3.14159\n9\n");
+
+    let output = run("class Empty {} print Empty.MISSING;");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Undefined property 'MISSING'.\n");
+}
+
+#[test]
+fn do_while_runs_the_body_at_least_once() {
+    let output = run(r#"
+        var i = 10;
+        do {
+            print i;
+            i++;
+        } while (i < 3);
+        var j = 0;
+        do j++; while (j < 3);
+        print j;
+    "#);
+
+    assert_eq!(output.stdout, "10\n3\n");
+}
+
+#[test]
+fn break_and_continue() {
+    let output = run(r#"
+        for (var i = 0; i < 5; i++) {
+            if (i == 1) continue;
+            if (i == 3) break;
+            write(i);
+        }
+        print "";
+        var n = 0;
+        while (true) {
+            n++;
+            if (n < 3) continue;
+            break;
+        }
+        print n;
+        for (x in [1, 2, 3]) {
+            if (x == 2) break;
+            print x;
+        }
+        do { break; print "unreachable"; } while (true);
+    "#);
+
+    assert_eq!(output.stdout, "02\n3\n1\n");
+}
+
+#[test]
+fn labeled_break_exits_the_outer_loop() {
+    let output = run(r#"
+        outer: for (var i = 0; i < 3; i++) {
+            inner: for (var j = 0; j < 3; j++) {
+                if (j == 1) continue outer;
+                if (i == 2) break outer;
+                write(i);
+                write(j);
+            }
+        }
+        print "";
+    "#);
+
+    assert_eq!(output.stdout, "0010\n");
+}
+
+#[test]
+fn break_must_target_an_enclosing_loop() {
+    let output = run("break;");
+    assert_eq!(output.code, 65);
+    assert_eq!(
+        output.stderr,
+        "Resolution error: Can't use 'break' outside of a loop.\n"
+    );
+
+    let output = run("while (true) { break missing; }");
+    assert_eq!(output.code, 65);
+    assert_eq!(
+        output.stderr,
+        "Resolution error: No enclosing loop labeled 'missing'.\n"
+    );
+
+    let output = run("while (true) { fun f() { continue; } }");
+    assert_eq!(output.code, 65);
+    assert_eq!(
+        output.stderr,
+        "Resolution error: Can't use 'continue' outside of a loop.\n"
+    );
+}
+
+#[test]
+fn string_natives() {
+    let output = run(r#"
+        print upper("Lox");
+        print lower("Lox");
+        print "[" + trim("  padded " + '\n') + "]";
+        print split("a,b,c", ",");
+        var count = 0;
+        for (part in split("a,b,c", ",")) count++;
+        print count;
+        print split("abc", "");
+        print split("", ",");
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "LOX\nlox\n[padded]\n[a, b, c]\n3\n[a, b, c]\n[]\n"
+    );
+
+    let output = run("upper(1);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Operand must be a string.\n");
+}
+
+#[test]
+fn join_native() {
+    let output = run(r#"
+        print join([1, 2, 3], "-");
+        print join(["a", nil, true, [1, 2]], ", ");
+        print join([], ",") == "";
+        print join(split("a b c", " "), "");
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "1-2-3\na, nil, true, [1, 2]\ntrue\nabc\n");
+
+    let output = run("join(\"abc\", \",\");");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Argument must be a list.\n");
+
+    let output = run("join([1], 2);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Operand must be a string.\n");
+}
+
+#[test]
+fn string_search_natives() {
+    let output = run(r#"
+        print contains("hello", "ell");
+        print contains("hello", "xyz");
+        print index_of("hello", "llo");
+        print index_of("hello", "xyz");
+        print index_of("héllo wörld", "wö");
+        print index_of("abc", "");
+    "#);
+
+    assert_eq!(output.stdout, "true\nfalse\n2\n-1\n6\n0\n");
+
+    let output = run("contains(\"a\", 1);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Operand must be a string.\n");
+}
+
+#[test]
+fn calling_non_callables_reports_the_type_and_line() {
+    let output = run("var x = 5;\nx();");
+    assert_eq!(output.code, 70);
+    assert_eq!(
+        output.stderr,
+        "Can only call functions and classes, got number.\n[line 2]\n"
+    );
+
+    let output = run("class Plain {}\nvar p = Plain();\n\np();");
+    assert_eq!(
+        output.stderr,
+        "Can only call functions and classes, got instance.\n[line 4]\n"
+    );
+}
+
+#[test]
+fn instances_with_a_call_method_are_callable() {
+    let output = run(r#"
+        class Adder {
+            init(n) { this.n = n; }
+            call(x) { return x + this.n; }
+        }
+        var add2 = Adder(2);
+        print add2(40);
+        print type(add2);
+    "#);
+
+    assert_eq!(output.stdout, "42\ninstance\n");
+
+    let output = run("class Adder { call(x) { return x; } } Adder()();");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Expected 1 arguments but got 0.\n");
+}
+
+#[test]
+fn parse_output_matches_reference_format() {
+    let cases = [
+        ("(1 + 2)", "(group (+ 1.0 2.0))"),
+        ("-(3)", "(- (group 3.0))"),
+        ("((5))", "(group (group 5.0))"),
+        ("!!true", "(! (! true))"),
+        ("-2 * 3", "(* (- 2.0) 3.0)"),
+        ("\"hello\" + nil", "(+ hello nil)"),
+        ("10.5 / 2 != 7", "(!= (/ 10.5 2.0) 7.0)"),
+        ("1 < 2 == (3 >= 4)", "(== (< 1.0 2.0) (group (>= 3.0 4.0)))"),
+        ("1 <= 2 > 3", "(> (<= 1.0 2.0) 3.0)"),
+        (
+            "-(1 + 2) * (3 - 4)",
+            "(* (- (group (+ 1.0 2.0))) (group (- 3.0 4.0)))",
+        ),
+    ];
+
+    for (source, expected) in cases {
+        let output = lox("parse", source);
+        assert_eq!(output.stdout, format!("{expected}\n"), "{source}");
+        assert_eq!(output.code, 0);
+    }
+}
+
+#[test]
+fn functions_print_like_reference_lox() {
+    let output = run(r#"
+        fun greet() {}
+        class Greeter {}
+        print greet;
+        print clock;
+        print Greeter;
+        print Greeter();
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "<fn greet>\n<native fn>\nGreeter\nGreeter instance\n"
+    );
+}
+
+#[test]
+fn invalid_utf8_source_is_rejected() {
+    let path = write_source("");
+    std::fs::write(&path, b"print \"\xff\xfe\";").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .arg("run")
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "[line 1] Error: source file is not valid UTF-8\n"
+    );
+}
+
+#[test]
+fn functions_are_first_class() {
+    let output = run(r#"
+        fun makeCounter() {
+            var count = 0;
+            fun counter() {
+                count = count + 1;
+                return count;
+            }
+            return counter;
+        }
+        var first = makeCounter();
+        var second = makeCounter();
+        print first();
+        print first();
+        print second();
+
+        fun apply(f, x) { return f(x); }
+        fun double(n) { return n * 2; }
+        print apply(double, 21);
+
+        var fns = [double, makeCounter()];
+        print fns[0](4);
+        print fns[1]();
+    "#);
+
+    assert_eq!(output.stdout, "1\n2\n1\n42\n8\n1\n");
+}
+
+#[test]
+fn methods_see_later_changes_to_captured_variables() {
+    let output = run(r#"
+        var greeting = "hello";
+        class Greeter {
+            greet() { return greeting; }
+        }
+        var greeter = Greeter();
+        greeting = "goodbye";
+        print greeter.greet();
+
+        fun scope() {
+            var count = 0;
+            class Counter {
+                read() { return count; }
+            }
+            var counter = Counter();
+            count = 5;
+            return counter;
+        }
+        print scope().read();
+    "#);
+
+    assert_eq!(output.stdout, "goodbye\n5\n");
+}
+
+#[test]
+fn global_reads_past_shadowing_locals() {
+    let output = run(r#"
+        var x = "global";
+        {
+            var x = "local";
+            print x;
+            print global("x");
+        }
+        fun f(x) { return global("x"); }
+        print f("param");
+    "#);
+
+    assert_eq!(output.stdout, "local\nglobal\nglobal\n");
+
+    let output = run(r#"global("missing");"#);
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Undefined variable 'missing'.\n");
+}
+
+#[test]
+fn list_concatenation() {
+    let output = run(r#"
+        var a = [1, 2];
+        var b = [3];
+        var c = a + b;
+        print c;
+        print a;
+        print b;
+        var alias = a;
+        a += b;
+        print alias;
+        a += a;
+        print a;
+        var n = 1;
+        n += 2;
+        print n;
+        var s = "a";
+        s += "b";
+        print s;
+    "#);
+
+    assert_eq!(
+        output.stdout,
+        "[1, 2, 3]\n[1, 2]\n[3]\n[1, 2, 3]\n[1, 2, 3, 1, 2, 3]\n3\nab\n"
+    );
+}
+
+#[test]
+fn plus_equal_on_fields() {
+    let output = run(r#"
+        class Cart {
+            init() {
+                this.items = [1];
+                this.total = 0;
+            }
+        }
+        var c = Cart();
+        var alias = c.items;
+        c.items += [2];
+        c.total += 5;
+        print c.items;
+        print alias;
+        print c.total;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "[1, 2]\n[1, 2]\n5\n");
+
+    let output = run("var a = [1]; a[0] += 1;");
+    assert_eq!(output.code, 65);
+    assert_eq!(
+        output.stderr,
+        "[line 1] Error at '+=': Invalid assignment target.\n"
+    );
+}
+
+#[test]
+fn lambdas_capture_this() {
+    let output = run(r#"
+        class Button {
+            init(label) {
+                this.label = label;
+                this.onClick = fun () { return "clicked " + this.label; };
+            }
+        }
+        var button = Button("ok");
+        var handler = button.onClick;
+        button.label = "cancel";
+        print handler();
+
+        var square = fun (x) { return x * x; };
+        print square(7);
+        print square;
+        fun (unused) {};
+    "#);
+
+    assert_eq!(output.stdout, "clicked cancel\n49\n<fn lambda>\n");
+}
+
+#[test]
+fn evaluate_resolves_like_run() {
+    let evaluated = lox("evaluate", "this");
+    let ran = run("print this;");
+
+    assert_eq!(evaluated.code, 65);
+    assert_eq!(evaluated.code, ran.code);
+    assert_eq!(evaluated.stderr, ran.stderr);
+    assert!(evaluated.stdout.is_empty());
+}
+
+#[test]
+fn print_multiple_values() {
+    let output = run(r#"
+        print 1, "x", true;
+        print "single";
+        var a = [1, 2];
+        print a[0], a, nil;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "1 x true\nsingle\n1 [1, 2] nil\n");
+}
+
+#[test]
+fn parameters_shadow_fields_and_globals() {
+    let output = run(r#"
+        var value = "global";
+        class Box {
+            init(value) {
+                this.value = value;
+            }
+            swap(value) {
+                var old = this.value;
+                this.value = value;
+                return old + " -> " + value;
+            }
+        }
+        fun show(value) {
+            {
+                var inner = value;
+                print inner;
+            }
+            return value;
+        }
+        var box = Box("field");
+        print box.swap("param");
+        print box.value;
+        print show("shadow");
+        print value;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "field -> param\nparam\nshadow\nshadow\nglobal\n"
+    );
+}
+
+#[test]
+fn non_finite_numbers_print_like_reference() {
+    let output = run("print 1 / 0; print -1 / 0; print 0 / 0;");
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "inf\n-inf\nnan\n");
+}
+
+#[test]
+fn blocks_with_and_without_declarations() {
+    let output = run(r#"
+        var a = "outer";
+        var total = 0;
+        for (var i = 0; i < 3; i = i + 1) {
+            { total = total + i; }
+            {
+                var a = "inner";
+                if (i == 2) print a;
+            }
+        }
+        { print a; }
+        print total;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "inner\nouter\n3\n");
+}
+
+#[test]
+fn chained_assignment() {
+    let output = run(r#"
+        var a; var b;
+        a = b = 5;
+        print a;
+        print b;
+        fun f() {
+            var c; var d;
+            print c = d = "x";
+            print c + d;
+        }
+        f();
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "5\n5\nx\nxx\n");
+}
+
+#[test]
+fn overriding_through_a_deep_chain() {
+    let output = run(r#"
+        class A { name() { return "A"; } base() { return "base"; } }
+        class B < A {}
+        class C < B { name() { return "C"; } }
+        class D < C {}
+        class E < D { name() { return "E " + super.name(); } }
+        print A().name();
+        print D().name();
+        print E().name();
+        print E().base();
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "A\nC\nE C\nbase\n");
+}
+
+#[test]
+fn unless_statement() {
+    let output = run(r#"
+        unless (false) print "yes";
+        unless (1 < 2) print "no"; else print "else";
+        var unlessy = "still an identifier prefix";
+        print unlessy;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "yes\nelse\nstill an identifier prefix\n");
+}
+
+#[test]
+fn format_number_native() {
+    let output = run(r#"
+        print format_number(3.14159, 2);
+        print format_number(2, 3);
+        print format_number(2.5, -1);
+        print type(format_number(1, 1));
+        print format_number(1 / 3, 50) == format_number(1 / 3, 20);
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "3.14\n2.000\n2\nstring\ntrue\n");
+}
+
+#[test]
+fn calling_the_result_of_a_call() {
+    let output = run(r#"
+        fun adder(a) {
+            fun add(b) { return a + b; }
+            return add;
+        }
+        print adder(3)(4);
+        var add3 = adder(3);
+        print add3(10);
+        fun curry(a) {
+            return fun(b) { return fun(c) { return a + b + c; }; };
+        }
+        print curry(1)(2)(3);
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "7\n13\n6\n");
+}
+
+#[test]
+fn block_expressions() {
+    let output = run(r#"
+        var a = "outer";
+        var x = {
+            var a = 2;
+            print a;
+            a * 10
+        };
+        print x;
+        print a;
+        // Inside a block, a leading `{` is a statement block, as at top level.
+        var nested = { { print "stmt"; } var one = { 1 }; one + { var b = 2; b } };
+        print nested;
+        fun f(n) {
+            return { if (n > 0) print "positive"; n + 1 };
+        }
+        print f(1);
+
+        // `{}` and `{ key: value }` stay map literals.
+        var empty = {};
+        var map = { k: 1 };
+        print type(empty);
+        print map["k"];
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "2\n20\nouter\nstmt\n3\npositive\n2\nmap\n1\n"
+    );
+}
+
+#[test]
+fn init_outside_classes_and_bare_returns_in_constructors() {
+    let output = run(r#"
+        fun init(x) { return x * 2; }
+        print init(4);
+
+        class Point {
+            init(x) {
+                this.x = x;
+                if (x > 0) return;
+                this.x = "negative";
+            }
+        }
+        var p = Point(1);
+        print p.x;
+        print Point(-1).x;
+        print p.init(5);
+        print p.x;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "8\n1\nnegative\nPoint instance\n5\n");
+}
+
+#[test]
+fn run_reports_parse_errors_up_to_the_limit() {
+    let path = write_source(&"print ;\n".repeat(5));
+    let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(["run", "--max-errors", "2"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "[line 1] Error at ';': Expect expression.\n\
+         [line 2] Error at ';': Expect expression.\n\
+         Too many errors; stopping.\n"
+    );
+}
+
+#[test]
+fn higher_order_list_natives() {
+    let output = run(r#"
+        var numbers = [1, 2, 3, 4];
+        print map(numbers, fun(n) { return n * 2; });
+        print filter(numbers, fun(n) { return n > 2; });
+        var total = 0;
+        print each(numbers, fun(n) { total = total + n; });
+        print total;
+        print map([], upper);
+        print numbers;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "[2, 4, 6, 8]\n[3, 4]\nnil\n10\n[]\n[1, 2, 3, 4]\n"
+    );
+
+    let output = run("print map([1], fun(a, b) { return a; });");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Callback must take one argument.\n");
+
+    let output = run("print filter(1, upper);");
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stderr, "Argument must be a list.\n");
+}
+
+#[test]
+fn maps_iterate_in_insertion_order() {
+    let output = run(r#"
+        var map = {zebra: 1, apple: 2, mango: 3, banana: 4};
+        print keys(map);
+        print values(map);
+        for (key in map) write(key + " ");
+        print "";
+        print map;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "[zebra, apple, mango, banana]\n[1, 2, 3, 4]\nzebra apple mango banana \n\
+         {zebra: 1, apple: 2, mango: 3, banana: 4}\n"
+    );
+}
+
+#[test]
+fn return_from_inside_a_loop_restores_the_caller_scope() {
+    let output = run(r#"
+        fun find(target) {
+            for (var i = 0; i < 10; i = i + 1) {
+                {
+                    var candidate = i;
+                    if (candidate == target) return candidate;
+                }
+            }
+            while (true) {
+                { return -1; }
+            }
+        }
+
+        var label = "outer";
+        {
+            var found = find(3);
+            var missing = find(42);
+            print label, found, missing;
+        }
+        var after = "still global";
+        print after;
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "outer 3 -1\nstill global\n");
+}
+
+#[test]
+fn super_calls_methods_but_not_fields() {
+    let output = run(r#"
+        class A {
+            init() { this.name = "a"; }
+            describe() { return "A with " + this.name; }
+        }
+        class B < A {
+            describe() { return "B, " + super.describe(); }
+            field() { return super.name; }
+        }
+        print B().describe();
+        print B().field();
+    "#);
+
+    assert_eq!(output.code, 70);
+    assert_eq!(output.stdout, "B, A with a\n");
+    assert_eq!(output.stderr, "Superclass 'A' has no method 'name'.\n");
+
+    let output = run("class A { f() { return super.f(); } }");
+    assert_eq!(output.code, 65);
+    assert_eq!(
+        output.stderr,
+        "Resolution error: Can't use 'super' in a class with no superclass.\n"
+    );
+}
+
+#[test]
+fn evaluate_prints_literals_like_the_reference() {
+    for (source, expected) in [
+        ("nil", "nil\n"),
+        ("true", "true\n"),
+        ("false", "false\n"),
+        ("42", "42\n"),
+        ("10.40", "10.4\n"),
+        ("-0.5", "-0.5\n"),
+        ("\"hello world\"", "hello world\n"),
+        ("\"\"", "\n"),
+        ("!nil", "true\n"),
+        ("1 == 1", "true\n"),
+    ] {
+        let output = lox("evaluate", source);
+        assert_eq!(output.code, 0, "{source}: {}", output.stderr);
+        assert_eq!(output.stdout, expected, "{source}");
+    }
+
+    // The token keeps the quotes; the value does not.
+    assert_eq!(
+        lox("tokenize", "\"hello\"").stdout,
+        "STRING \"hello\" hello\nEOF  null\n"
+    );
+}
+
+#[test]
+fn redefining_a_global_function() {
+    let output = run(r#"
+        fun f(a) { return "old " + a; }
+        var saved = f;
+        fun callsF() { return f("x", "y"); }
+        fun f(a, b) { return "new " + a + b; }
+        print saved("a");
+        print f("b", "c");
+        print callsF();
+    "#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "old a\nnew bc\nnew xy\n");
+}
+
+#[test]
+fn tokenize_summary() {
+    let path = write_source("var x = @;\nprint x;");
+    let tokenize = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .arg("tokenize")
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap()
+    };
+
+    let output = tokenize(&["--summary"]);
+    assert_eq!(output.status.code(), Some(65));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        "[line 1] Error: Unexpected character: @\n# 8 tokens, 1 error\n"
+    );
+
+    let output = tokenize(&["--json", "--summary"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "# 8 tokens, 1 error\n");
+
+    // Without the flag, nothing extra is written.
+    let output = tokenize(&[]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "[line 1] Error: Unexpected character: @\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn long_else_if_chains() {
+    let mut source = String::from("var x = 750;\nif (x == 0) print 0;");
+    for i in 1..=1000 {
+        source.push_str(&format!("\nelse if (x == {i}) print \"branch {i}\";"));
+    }
+    source.push_str("\nelse print \"none\";");
+
+    let output = run(&source);
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "branch 750\n");
+}
+
+#[test]
+fn import_runs_files_relative_to_the_importer() {
+    let dir = std::env::temp_dir().join(format!("lox-import-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("lib/greet.lox"),
+        r#"
+        // Importing back into the importer, or twice, runs nothing again.
+        import "../main.lox";
+        import "words.lox";
+        fun greet(name) { return hello + ", " + name + "!"; }
+        print "loaded greet";
+        "#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("lib/words.lox"), r#"var hello = "Hello";"#).unwrap();
+    std::fs::write(dir.join("broken.lox"), "var;").unwrap();
+    std::fs::write(
+        dir.join("main.lox"),
+        r#"
+        import "lib/greet.lox";
+        import "lib/greet.lox";
+        print greet("Lox");
+        "#,
+    )
+    .unwrap();
+
+    let run_file = |name: &str| {
+        Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+            .arg("run")
+            .arg(dir.join(name))
+            .output()
+            .unwrap()
+    };
+
+    let output = run_file("main.lox");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "loaded greet\nHello, Lox!\n"
+    );
+    assert_eq!(output.status.code(), Some(0));
+
+    std::fs::write(dir.join("missing.lox"), r#"import "nowhere.lox";"#).unwrap();
+    let output = run_file("missing.lox");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Can't import 'nowhere.lox': "));
+
+    std::fs::write(dir.join("bad_import.lox"), r#"import "broken.lox";"#).unwrap();
+    let output = run_file("bad_import.lox");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("In 'broken.lox': "));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn single_character_escapes_are_literal_in_double_quoted_strings() {
+    let output = run(r#"print "C:\"; print "a\nb"; print '\n' == "a\nb";"#);
+
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "C:\\\na\\nb\nfalse\n");
+    assert_eq!(
+        lox("tokenize", r#""\n""#).stdout,
+        "STRING \"\\n\" \\n\nEOF  null\n"
+    );
+}
+
+#[test]
+fn numeric_escapes_still_decode_in_double_quoted_strings() {
+    let output = run(r#"print "C:\Users\new"; print "\x48\u{69}";"#);
+    assert_eq!(output.code, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "C:\\Users\\new\nHi\n");
+
+    // `\u` always starts a `\u{…}` escape, so this path is a lex error.
+    let output = lox("tokenize", r#"print "C:\users";"#);
+    assert_eq!(output.code, 65);
+    assert_eq!(output.stderr, "[line 1] Error: Unexpected character: \\u\n");
+}